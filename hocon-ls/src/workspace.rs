@@ -1,45 +1,24 @@
 use std::collections::HashMap;
 
-use hocon_rs::parser::{parse, HoconError, HoconValue};
-use nom_language::error::VerboseError;
+use hocon_rs::parser::{HoconError, HoconValue};
 
 pub struct Workspace {
     open_files: HashMap<String, OpenFile>,
 }
 
-// Must remain private to hide nasty unsafe properties
-#[allow(clippy::box_collection)]
 struct OpenFile {
     hocon: HoconValue<'static>,
-    // Must box to avoid moves breaking the hocon value
-    content: Box<String>,
 }
 
-impl <'a> OpenFile {
-    fn new(content: String) -> Result<OpenFile, HoconError> {
-        let content = Box::new(content);
-        match parse::<VerboseError<&str>>(&content) {
-            Ok(hocon) => {
-                Ok(OpenFile {
-                    // As long as access to hocon is restricted to the lifetime of OpenFile, this
-                    // transmute is safe.
-                    hocon: unsafe { std::mem::transmute(hocon) },
-                    content
-                })
-            }
-            Err(e) => Err(e)
-        }
-    }
-
-    pub fn get_ast(&self) -> &HoconValue<'a> {
-        // Expose the wrong 'static back as 'a lifetime.
-        // This forces the borrow check back in live
-        unsafe { std::mem::transmute(&self.hocon) }
+impl OpenFile {
+    fn new(content: &str) -> Result<OpenFile, HoconError> {
+        Ok(OpenFile {
+            hocon: content.parse()?,
+        })
     }
 }
 
-impl<'a> Workspace {
-
+impl Workspace {
     pub fn new() -> Self {
         Workspace {
             open_files: HashMap::new(),
@@ -47,12 +26,13 @@ impl<'a> Workspace {
     }
 
     pub fn open_file(&mut self, path: String, content: String) -> Result<(), HoconError> {
-        let hocon = OpenFile::new(content)?;
+        let hocon = OpenFile::new(&content)?;
         self.open_files.insert(path, hocon);
         Ok(())
     }
 
-    pub fn get_ast(&self, path: &str) -> Option<&HoconValue<'a>> {
-        self.open_files.get(path).map(|file| file.get_ast())
+    #[allow(dead_code)]
+    pub fn get_ast(&self, path: &str) -> Option<&HoconValue<'static>> {
+        self.open_files.get(path).map(|file| &file.hocon)
     }
 }
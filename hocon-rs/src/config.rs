@@ -0,0 +1,604 @@
+use std::{num::IntErrorKind, path::Path, str::FromStr, sync::Arc};
+
+use indexmap::IndexMap;
+use thiserror::Error;
+
+use crate::{
+    include::Loader,
+    parser::{self, HoconError, HoconField, HoconPath, HoconString, HoconValue},
+    resolve::resolve,
+    span::{ConfigOrigin, SourceMap, Span, Spanned},
+};
+
+/// An error loading a [`Config`] or reading a value from it.
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Hocon(#[from] HoconError),
+    /// A path which was requested but has no value, or only a null value.
+    #[error("No value at {path} ({origin})")]
+    Missing { path: String, origin: ConfigOrigin },
+    #[error("Expected {expected} at {path} but found {found} ({origin})")]
+    WrongType {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+        origin: ConfigOrigin,
+    },
+    /// A value of the right type which can't be used, such as a number which is out of range.
+    #[error("Invalid value at {path}: {message} ({origin})")]
+    BadValue {
+        path: String,
+        message: String,
+        origin: ConfigOrigin,
+    },
+}
+
+/// A value of a resolved configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigValue {
+    Object(Config),
+    List(Vec<ConfigValue>),
+    String(String),
+    Int(i64),
+    /// An integer which doesn't fit in an `i64`, kept as its literal text.
+    BigInt(String),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+impl ConfigValue {
+    /// The name of the type of the value, as used in [`ConfigError::WrongType`].
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConfigValue::Object(_) => "object",
+            ConfigValue::List(_) => "list",
+            ConfigValue::String(_) => "string",
+            ConfigValue::Int(_) | ConfigValue::BigInt(_) => "int",
+            ConfigValue::Float(_) => "float",
+            ConfigValue::Bool(_) => "boolean",
+            ConfigValue::Null => "null",
+        }
+    }
+}
+
+/// A resolved configuration, which owns all of its values and looks them up by their path.
+///
+/// Paths are path expressions like the ones of substitutions, such that `a.b."c.d"` refers to the key `c.d` in the
+/// object `b` within the object `a`. A null value is treated as a missing value by the getters, use [`Config::is_null`]
+/// to tell them apart.
+///
+/// Strings are converted to the type requested from them when possible, as Java properties only contain strings:
+/// `"42"` can be read with [`Config::get_int`] and `"yes"` or `"on"` with [`Config::get_bool`]. Numbers and booleans
+/// can in turn be read as strings.
+///
+/// Configurations are compared by their values, ignoring where they were defined.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// The documents the configuration was loaded from, shared by all objects within it.
+    sources: Arc<SourceMap>,
+    span: Option<Span>,
+    fields: IndexMap<String, Spanned<ConfigValue>>,
+}
+
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    /// Parses and resolves a HOCON document, of which includes are not loaded.
+    fn from_str(input: &str) -> Result<Self, ConfigError> {
+        let mut sources = SourceMap::default();
+        sources.add(None, input.to_owned());
+        Config::from_document(parser::parse(input)?, sources)
+    }
+}
+
+impl Config {
+    /// Loads the document at the path along with the documents it includes, see [`Loader`]. The origin of each value
+    /// names the document it was defined in, which is an included document for values which were included.
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let (document, sources) = Loader::default().load_file_with_sources(path)?;
+        Config::from_document(document, sources)
+    }
+
+    /// Resolves the substitutions of the document, of which the includes must already have been loaded, and converts
+    /// it into a configuration. The spans of the document are located in the sources to report the origin of its
+    /// values, which may be empty when the document wasn't parsed from any.
+    pub fn from_document(document: HoconValue<'_>, sources: SourceMap) -> Result<Config, ConfigError> {
+        let root = Config {
+            sources: Arc::new(sources),
+            ..Config::default()
+        };
+        match resolve(document)? {
+            HoconValue::HoconObject(fields) => root.object(fields, None, &[]),
+            value => Err(ConfigError::WrongType {
+                path: String::new(),
+                expected: "object",
+                found: root.value(value, Span::default(), &[])?.type_name(),
+                origin: root.origin(None),
+            }),
+        }
+    }
+
+    fn object(&self, fields: Vec<HoconField<'_>>, span: Option<Span>, path: &[String]) -> Result<Config, ConfigError> {
+        let mut config = Config {
+            sources: self.sources.clone(),
+            span,
+            fields: IndexMap::new(),
+        };
+        for field in fields {
+            match field {
+                HoconField::KeyValue(key, value) => {
+                    // Resolving expands the keys into nested objects, so each of them is a single segment.
+                    let key = key.segments.concat();
+                    let path = [path, std::slice::from_ref(&key)].concat();
+                    let converted = self.value(value.value, value.span, &path)?;
+                    config.fields.insert(key, Spanned::new(converted, value.span));
+                }
+                HoconField::Append(_, value, _) => {
+                    return Err(self.bad_value(path, "append was not resolved", value.span));
+                }
+                HoconField::Include(inclusion) => {
+                    let message = format!("include of {} was not loaded", inclusion.value.resource.name());
+                    return Err(self.bad_value(path, &message, inclusion.span));
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    fn value(&self, value: HoconValue<'_>, span: Span, path: &[String]) -> Result<ConfigValue, ConfigError> {
+        Ok(match value {
+            HoconValue::HoconObject(fields) => ConfigValue::Object(self.object(fields, Some(span), path)?),
+            HoconValue::HoconArray(values) => ConfigValue::List(
+                values
+                    .into_iter()
+                    .map(|value| self.value(value.value, value.span, path))
+                    .collect::<Result<_, _>>()?,
+            ),
+            HoconValue::HoconString(
+                HoconString::Quoted(value) | HoconString::Unqouted(value) | HoconString::Multiline(value),
+            ) => ConfigValue::String(value.into_owned()),
+            HoconValue::Int(value, _) => ConfigValue::Int(value),
+            HoconValue::Float(value, _) => ConfigValue::Float(value),
+            HoconValue::BigInt(literal) => ConfigValue::BigInt(literal.into_owned()),
+            HoconValue::HoconBoolean(value) => ConfigValue::Bool(value),
+            HoconValue::HoconNull => ConfigValue::Null,
+            HoconValue::HoconInclude(inclusion) => {
+                let message = format!("include of {} was not loaded", inclusion.resource.name());
+                return Err(self.bad_value(path, &message, span));
+            }
+            HoconValue::Substitution { .. } | HoconValue::Concatenation(_) => {
+                return Err(self.bad_value(path, "value was not resolved", span));
+            }
+        })
+    }
+
+    fn bad_value(&self, path: &[String], message: &str, span: Span) -> ConfigError {
+        ConfigError::BadValue {
+            path: HoconPath::new(path.iter().map(|s| s.clone().into()).collect()).to_string(),
+            message: message.to_owned(),
+            origin: self.origin(Some(span)),
+        }
+    }
+
+    fn origin(&self, span: Option<Span>) -> ConfigOrigin {
+        self.sources.origin(span)
+    }
+
+    /// Looks up the value at the path, which may be null.
+    fn find(&self, path: &str) -> Result<&Spanned<ConfigValue>, ConfigError> {
+        let parsed = parser::parse_path(path).map_err(|e| ConfigError::BadValue {
+            path: path.to_owned(),
+            message: e.to_string(),
+            origin: self.origin(self.span),
+        })?;
+        let mut config = self;
+        for (i, segment) in parsed.segments.iter().enumerate() {
+            let missing = || ConfigError::Missing {
+                path: path.to_owned(),
+                origin: config.origin(config.span),
+            };
+            let value = config.fields.get(segment.as_ref()).ok_or_else(missing)?;
+            if i + 1 == parsed.segments.len() {
+                return Ok(value);
+            }
+            config = match &value.value {
+                ConfigValue::Object(object) => object,
+                ConfigValue::Null => return Err(missing()),
+                other => {
+                    return Err(ConfigError::WrongType {
+                        path: HoconPath::new(parsed.segments[..=i].to_vec()).to_string(),
+                        expected: "object",
+                        found: other.type_name(),
+                        origin: config.origin(Some(value.span)),
+                    })
+                }
+            };
+        }
+        unreachable!("a parsed path has at least one segment")
+    }
+
+    /// Looks up the value at the path along with its origin, treating a null value as missing.
+    fn get(&self, path: &str) -> Result<(&ConfigValue, ConfigOrigin), ConfigError> {
+        let value = self.find(path)?;
+        let origin = self.origin(Some(value.span));
+        match value.value {
+            ConfigValue::Null => Err(ConfigError::Missing {
+                path: path.to_owned(),
+                origin,
+            }),
+            ref value => Ok((value, origin)),
+        }
+    }
+
+    fn wrong_type(path: &str, expected: &'static str, found: &'static str, origin: ConfigOrigin) -> ConfigError {
+        ConfigError::WrongType {
+            path: path.to_owned(),
+            expected,
+            found,
+            origin,
+        }
+    }
+
+    /// Whether there is a value at the path which isn't null.
+    pub fn has_path(&self, path: &str) -> bool {
+        self.get(path).is_ok()
+    }
+
+    /// Whether the value at the path is null, failing when there is no value at all.
+    pub fn is_null(&self, path: &str) -> Result<bool, ConfigError> {
+        Ok(self.find(path)?.value == ConfigValue::Null)
+    }
+
+    /// The value at the path, of any type.
+    pub fn get_value(&self, path: &str) -> Result<&ConfigValue, ConfigError> {
+        self.get(path).map(|(value, _)| value)
+    }
+
+    pub fn get_string(&self, path: &str) -> Result<String, ConfigError> {
+        match self.get(path)? {
+            (ConfigValue::String(value), _) => Ok(value.clone()),
+            (ConfigValue::Int(value), _) => Ok(value.to_string()),
+            (ConfigValue::BigInt(literal), _) => Ok(literal.clone()),
+            (ConfigValue::Float(value), _) => Ok(value.to_string()),
+            (ConfigValue::Bool(value), _) => Ok(value.to_string()),
+            (value, origin) => Err(Config::wrong_type(path, "string", value.type_name(), origin)),
+        }
+    }
+
+    pub fn get_bool(&self, path: &str) -> Result<bool, ConfigError> {
+        match self.get(path)? {
+            (ConfigValue::Bool(value), _) => Ok(*value),
+            (value @ ConfigValue::String(text), origin) => match text.as_str() {
+                "true" | "yes" | "on" => Ok(true),
+                "false" | "no" | "off" => Ok(false),
+                _ => Err(Config::wrong_type(path, "boolean", value.type_name(), origin)),
+            },
+            (value, origin) => Err(Config::wrong_type(path, "boolean", value.type_name(), origin)),
+        }
+    }
+
+    /// The integer at the path, failing with [`ConfigError::BadValue`] when it doesn't fit in an `i32`.
+    pub fn get_int(&self, path: &str) -> Result<i32, ConfigError> {
+        let (value, origin) = self.integer(path)?;
+        value.try_into().map_err(|_| ConfigError::BadValue {
+            path: path.to_owned(),
+            message: format!("{value} is out of range for an int"),
+            origin,
+        })
+    }
+
+    /// The integer at the path. Floats are not converted, even when they are whole numbers.
+    pub fn get_i64(&self, path: &str) -> Result<i64, ConfigError> {
+        self.integer(path).map(|(value, _)| value)
+    }
+
+    fn integer(&self, path: &str) -> Result<(i64, ConfigOrigin), ConfigError> {
+        match self.get(path)? {
+            (ConfigValue::Int(value), origin) => Ok((*value, origin)),
+            (ConfigValue::BigInt(literal), origin) => Err(ConfigError::BadValue {
+                path: path.to_owned(),
+                message: format!("{literal} is out of range for an i64"),
+                origin,
+            }),
+            (value @ ConfigValue::String(text), origin) => match text.parse() {
+                Ok(parsed) => Ok((parsed, origin)),
+                Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow | IntErrorKind::NegOverflow) => {
+                    Err(ConfigError::BadValue {
+                        path: path.to_owned(),
+                        message: format!("{text} is out of range for an i64"),
+                        origin,
+                    })
+                }
+                Err(_) => Err(Config::wrong_type(path, "int", value.type_name(), origin)),
+            },
+            (value, origin) => Err(Config::wrong_type(path, "int", value.type_name(), origin)),
+        }
+    }
+
+    pub fn get_f64(&self, path: &str) -> Result<f64, ConfigError> {
+        match self.get(path)? {
+            (ConfigValue::Float(value), _) => Ok(*value),
+            (ConfigValue::Int(value), _) => Ok(*value as f64),
+            (value @ (ConfigValue::BigInt(text) | ConfigValue::String(text)), origin) => text
+                .parse()
+                .map_err(|_| Config::wrong_type(path, "number", value.type_name(), origin)),
+            (value, origin) => Err(Config::wrong_type(path, "number", value.type_name(), origin)),
+        }
+    }
+
+    pub fn get_list(&self, path: &str) -> Result<&[ConfigValue], ConfigError> {
+        match self.get(path)? {
+            (ConfigValue::List(values), _) => Ok(values),
+            (value, origin) => Err(Config::wrong_type(path, "list", value.type_name(), origin)),
+        }
+    }
+
+    /// The object at the path as a configuration, of which paths are relative to that object.
+    pub fn get_config(&self, path: &str) -> Result<&Config, ConfigError> {
+        match self.get(path)? {
+            (ConfigValue::Object(config), _) => Ok(config),
+            (value, origin) => Err(Config::wrong_type(path, "object", value.type_name(), origin)),
+        }
+    }
+
+    /// The keys of the fields of this object along with their values, in the order they were defined.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ConfigValue)> {
+        self.fields.iter().map(|(key, value)| (key.as_str(), &value.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{properties::parse_properties, span::Position};
+
+    fn config(content: &str) -> Config {
+        content.parse().unwrap()
+    }
+
+    fn at(line: usize, column: usize) -> ConfigOrigin {
+        ConfigOrigin {
+            name: None,
+            position: Some(Position { line, column }),
+        }
+    }
+
+    #[test]
+    fn test_getters() {
+        let config = config(
+            r#"
+            name = service
+            server { port = 8080, ratio = 0.5, enabled = true }
+            hosts = [a, b]
+            "a.b" = ${server.port}
+            "#,
+        );
+        assert_eq!(config.get_string("name"), Ok("service".to_string()));
+        assert_eq!(config.get_int("server.port"), Ok(8080));
+        assert_eq!(config.get_i64("server.port"), Ok(8080));
+        assert_eq!(config.get_f64("server.ratio"), Ok(0.5));
+        assert_eq!(config.get_f64("server.port"), Ok(8080.0));
+        assert_eq!(config.get_bool("server.enabled"), Ok(true));
+        assert_eq!(config.get_string("server.port"), Ok("8080".to_string()));
+        assert_eq!(config.get_int("\"a.b\""), Ok(8080));
+        assert_eq!(
+            config.get_list("hosts"),
+            Ok(&[ConfigValue::String("a".into()), ConfigValue::String("b".into())][..])
+        );
+        let server = config.get_config("server").unwrap();
+        assert_eq!(server.get_int("port"), Ok(8080));
+        assert_eq!(
+            server.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            vec!["port", "ratio", "enabled"]
+        );
+    }
+
+    #[test]
+    fn test_string_conversions() {
+        let config = Config::from_document(
+            parse_properties("a = 42\nb = yes\nc = off\nd = 1.5").unwrap(),
+            SourceMap::default(),
+        )
+        .unwrap();
+        assert_eq!(config.get_int("a"), Ok(42));
+        assert_eq!(config.get_bool("b"), Ok(true));
+        assert_eq!(config.get_bool("c"), Ok(false));
+        assert_eq!(config.get_f64("d"), Ok(1.5));
+        assert_eq!(config.get_string("d"), Ok("1.5".to_string()));
+    }
+
+    #[test]
+    fn test_missing() {
+        let config = config("a { b = 1 }\nc = null");
+        assert_eq!(
+            config.get_int("a.x"),
+            Err(ConfigError::Missing {
+                path: "a.x".to_string(),
+                origin: at(0, 2),
+            })
+        );
+        assert_eq!(
+            config.get_int("x"),
+            Err(ConfigError::Missing {
+                path: "x".to_string(),
+                origin: ConfigOrigin::default(),
+            })
+        );
+        assert_eq!(
+            config.get_string("c"),
+            Err(ConfigError::Missing {
+                path: "c".to_string(),
+                origin: at(1, 4),
+            })
+        );
+        assert!(matches!(config.get_int("c.d"), Err(ConfigError::Missing { .. })));
+        assert_eq!(
+            config.get_string("x").unwrap_err().to_string(),
+            "No value at x (document)"
+        );
+        assert_eq!(
+            config.get_string("c").unwrap_err().to_string(),
+            "No value at c (line 2, column 5)"
+        );
+    }
+
+    #[test]
+    fn test_has_path_and_is_null() {
+        let config = config("a { b = 1 }\nc = null");
+        assert!(config.has_path("a"));
+        assert!(config.has_path("a.b"));
+        assert!(!config.has_path("a.c"));
+        assert!(!config.has_path("c"));
+        assert_eq!(config.is_null("c"), Ok(true));
+        assert_eq!(config.is_null("a.b"), Ok(false));
+        assert!(matches!(config.is_null("d"), Err(ConfigError::Missing { .. })));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let config = config("a = abc\nb { c = [1] }");
+        assert_eq!(
+            config.get_int("a"),
+            Err(ConfigError::WrongType {
+                path: "a".to_string(),
+                expected: "int",
+                found: "string",
+                origin: at(0, 4),
+            })
+        );
+        assert!(matches!(
+            config.get_bool("a"),
+            Err(ConfigError::WrongType {
+                expected: "boolean",
+                found: "string",
+                ..
+            })
+        ));
+        assert!(matches!(
+            config.get_f64("a"),
+            Err(ConfigError::WrongType {
+                expected: "number",
+                found: "string",
+                ..
+            })
+        ));
+        assert!(matches!(
+            config.get_config("b.c"),
+            Err(ConfigError::WrongType {
+                expected: "object",
+                found: "list",
+                ..
+            })
+        ));
+        assert!(matches!(
+            config.get_string("b"),
+            Err(ConfigError::WrongType {
+                expected: "string",
+                found: "object",
+                ..
+            })
+        ));
+        // A value within a path which isn't an object is reported for the part of the path leading to it.
+        assert!(matches!(
+            config.get_int("a.b"),
+            Err(ConfigError::WrongType { path, expected: "object", .. }) if path == "a"
+        ));
+    }
+
+    #[test]
+    fn test_bad_value() {
+        let config = config("a = 3000000000\nb = \"99999999999999999999\"");
+        assert_eq!(config.get_i64("a"), Ok(3_000_000_000));
+        assert_eq!(
+            config.get_int("a"),
+            Err(ConfigError::BadValue {
+                path: "a".to_string(),
+                message: "3000000000 is out of range for an int".to_string(),
+                origin: at(0, 4),
+            })
+        );
+        assert!(matches!(config.get_i64("b"), Err(ConfigError::BadValue { .. })));
+        assert!(matches!(config.get_int("a..b"), Err(ConfigError::BadValue { path, .. }) if path == "a..b"));
+    }
+
+    #[test]
+    fn test_big_int() {
+        let config = config("a = 99999999999999999999");
+        assert_eq!(
+            config.get_value("a"),
+            Ok(&ConfigValue::BigInt("99999999999999999999".to_string()))
+        );
+        assert_eq!(config.get_string("a"), Ok("99999999999999999999".to_string()));
+        assert_eq!(config.get_f64("a"), Ok(1e20));
+        assert_eq!(
+            config.get_i64("a"),
+            Err(ConfigError::BadValue {
+                path: "a".to_string(),
+                message: "99999999999999999999 is out of range for an i64".to_string(),
+                origin: at(0, 4),
+            })
+        );
+        assert!(matches!(config.get_int("a"), Err(ConfigError::BadValue { .. })));
+    }
+
+    #[test]
+    fn test_root_which_is_not_an_object() {
+        let error = Config::from_document(HoconValue::Int(1, "1".into()), SourceMap::default()).unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::WrongType {
+                expected: "object",
+                found: "int",
+                ..
+            }
+        ));
+        let error = Config::from_document(HoconValue::HoconArray(vec![]), SourceMap::default()).unwrap_err();
+        assert!(matches!(error, ConfigError::WrongType { found: "list", .. }));
+    }
+
+    #[test]
+    fn test_unresolved_include() {
+        let error = Config::from_str("a { include file(\"b.conf\") }").unwrap_err();
+        assert!(matches!(error, ConfigError::BadValue { path, .. } if path == "a"));
+    }
+
+    #[test]
+    fn test_load_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("application.conf");
+        fs::write(&path, "include \"defaults\"\nport = 80").unwrap();
+        fs::write(dir.path().join("defaults.conf"), "port = 8080\nhost = localhost").unwrap();
+
+        let config = Config::load_file(&path).unwrap();
+        assert_eq!(config.get_int("port"), Ok(80));
+        assert_eq!(config.get_string("host"), Ok("localhost".to_string()));
+        // Values report the document they were defined in, including the ones which were included.
+        let dir = dir.path().canonicalize().unwrap();
+        let error = config.get_int("host").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Expected int at host but found string ({}:2:8)",
+                dir.join("defaults.conf").display()
+            )
+        );
+        let error = config.get_bool("port").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Expected boolean at port but found int ({}:2:8)",
+                dir.join("application.conf").display()
+            )
+        );
+    }
+}
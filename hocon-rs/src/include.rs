@@ -0,0 +1,1082 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    parser::{
+        parse_with_syntax, ConfigSyntax, HoconError, HoconField, HoconInclusion, HoconPath, HoconValue, IncludeResource,
+    },
+    properties::parse_properties,
+    span::{SourceMap, Span, Spanned},
+};
+
+/// Loads the document at the path, replacing its includes with the contents of the files they refer to.
+///
+/// This uses a [`Loader`] with the [`DefaultIncluder`], see [`Loader::load_includes`] for how included documents are
+/// merged into the document.
+pub fn load_file(path: impl AsRef<Path>) -> Result<HoconValue<'static>, HoconError> {
+    Loader::default().load_file(path)
+}
+
+/// Replaces the includes in the document with the contents of the files they refer to, recursively.
+///
+/// This uses a [`Loader`] with the [`DefaultIncluder`], see [`Loader::load_includes`] for the details.
+pub fn load_includes<'a>(document: HoconValue<'a>, base: impl AsRef<Path>) -> Result<HoconValue<'a>, HoconError> {
+    Loader::default().load_includes(document, base)
+}
+
+/// Reads the resources referred to by includes.
+///
+/// Each method returns the content of the resource, or an error of kind [`io::ErrorKind::NotFound`] when it doesn't
+/// exist, in which case the include is ignored unless it is required. By default files are read from disk, while
+/// classpath resources and urls are unsupported.
+pub trait Includer {
+    /// Reads the file for `include file("path")`, or `include "path"` within a file.
+    fn include_file(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    /// Reads the resource for `include classpath("name")`, or `include "name"` within a classpath resource. The name
+    /// is relative to the root of the classpath.
+    fn include_classpath(&self, name: &str) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Classpath resources are not supported, cannot include {name}"),
+        ))
+    }
+
+    /// Reads the resource for `include url("url")`, or `include "url"` when the name has a scheme.
+    fn include_url(&self, url: &str) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Urls are not supported, cannot include {url}"),
+        ))
+    }
+}
+
+/// Fetches the content of a url.
+type Fetch = Box<dyn Fn(&str) -> io::Result<String> + Send + Sync>;
+
+/// The [`Includer`] used by default, which reads files from disk.
+///
+/// Classpath resources are looked up in the embedded resources first, and then in each of the classpath directories in
+/// the order they were added. Urls are only supported when a fetch callback is configured.
+#[derive(Default)]
+pub struct DefaultIncluder {
+    resources: HashMap<String, Cow<'static, str>>,
+    classpath: Vec<PathBuf>,
+    fetch: Option<Fetch>,
+}
+
+impl DefaultIncluder {
+    /// Adds a resource to the classpath which is kept in memory, such as a file embedded with `include_str!`.
+    pub fn with_resource(mut self, name: impl Into<String>, content: impl Into<Cow<'static, str>>) -> Self {
+        self.resources.insert(name.into(), content.into());
+        self
+    }
+
+    /// Adds a directory in which classpath resources are looked up.
+    pub fn with_classpath_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.classpath.push(dir.into());
+        self
+    }
+
+    /// Sets the callback which fetches the content of included urls.
+    pub fn with_url_fetcher(mut self, fetch: impl Fn(&str) -> io::Result<String> + Send + Sync + 'static) -> Self {
+        self.fetch = Some(Box::new(fetch));
+        self
+    }
+}
+
+impl Includer for DefaultIncluder {
+    fn include_classpath(&self, name: &str) -> io::Result<String> {
+        if let Some(content) = self.resources.get(name) {
+            return Ok(content.to_string());
+        }
+        for dir in &self.classpath {
+            match fs::read_to_string(dir.join(name)) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                result => return result,
+            }
+        }
+        Err(io::ErrorKind::NotFound.into())
+    }
+
+    fn include_url(&self, url: &str) -> io::Result<String> {
+        match &self.fetch {
+            Some(fetch) => fetch(url),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("No url fetcher is configured, cannot include {url}"),
+            )),
+        }
+    }
+}
+
+/// Loads documents along with the documents they include, reading them through an [`Includer`].
+///
+/// The syntax of each document is inferred from the extension of its name. A name without one of the extensions
+/// `.conf`, `.json` or `.properties` refers to each of the documents with the name and one of those extensions, of
+/// which all that exist are loaded and merged: the fields of `.conf` documents take precedence over those of `.json`
+/// documents, which take precedence over those of `.properties` documents. Including `"application"` thus loads
+/// `application.conf`, `application.json` and `application.properties`. When none of those exist, the document with
+/// the name itself is loaded as HOCON.
+///
+/// The root of every loaded document must be an object, a JSON document with an array root results in
+/// [`HoconError::RootArray`].
+pub struct Loader<I = DefaultIncluder> {
+    includer: I,
+    syntax: Option<ConfigSyntax>,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Loader::new(DefaultIncluder::default())
+    }
+}
+
+impl<I: Includer> Loader<I> {
+    pub fn new(includer: I) -> Self {
+        Loader { includer, syntax: None }
+    }
+
+    /// Parses the documents loaded directly by this loader in the given syntax regardless of their name, which also
+    /// stops the loader from trying other extensions. Included documents still infer their syntax from their name.
+    pub fn with_syntax(mut self, syntax: ConfigSyntax) -> Self {
+        self.syntax = Some(syntax);
+        self
+    }
+
+    /// Loads the document at the path, of which relative includes are resolved against the directory of the file.
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<HoconValue<'static>, HoconError> {
+        self.load(Origin::File(path.as_ref().to_path_buf()))
+    }
+
+    /// Loads the document at the path like [`Loader::load_file`], along with the [`SourceMap`] of the documents it was
+    /// loaded from. The spans of each loaded document are moved past the ones of the documents loaded before it, so
+    /// the map can tell which document a span is located in, including the ones of included values.
+    pub fn load_file_with_sources(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(HoconValue<'static>, SourceMap), HoconError> {
+        let mut loading = self.loading();
+        loading.sources = Some(SourceMap::default());
+        let fields = loading.load(Origin::File(path.as_ref().to_path_buf()), self.syntax)?;
+        Ok((HoconValue::HoconObject(fields), loading.sources.unwrap_or_default()))
+    }
+
+    /// Loads the classpath resource, of which relative includes are resolved against the resource.
+    pub fn load_classpath(&self, name: &str) -> Result<HoconValue<'static>, HoconError> {
+        self.load(Origin::Classpath(name.trim_start_matches('/').to_owned()))
+    }
+
+    /// Loads the document at the url, of which relative includes are resolved against the url.
+    pub fn load_url(&self, url: &str) -> Result<HoconValue<'static>, HoconError> {
+        self.load(Origin::Url(url.to_owned()))
+    }
+
+    /// Replaces the includes in the document with the contents of the resources they refer to, recursively.
+    ///
+    /// Relative file names are resolved against `base`, the directory containing the document, while the includes of
+    /// an included document are resolved against the document itself: `include "name"` within a classpath resource
+    /// refers to another classpath resource and within a url to another url. A name with a scheme such as
+    /// `include "https://example.com/a.conf"` always refers to a url.
+    ///
+    /// The fields of the included document take the place of the include, so they are merged with the surrounding
+    /// fields as if they had been written there. For an include within a nested object, the substitutions of the
+    /// included document are made relative to that object: with `a { include file("b.conf") }` the substitution
+    /// `${x}` within `b.conf` refers to `a.x`. An include used as a value, such as `a = include file("b.conf")`, is
+    /// replaced by an object with the fields of the included document in the same way.
+    ///
+    /// An include of a resource which doesn't exist is ignored, unless it is wrapped in `required(...)` which results
+    /// in [`HoconError::IncludeNotFound`]. A document which includes itself, directly or through other documents,
+    /// results in [`HoconError::IncludeCycle`].
+    ///
+    /// The spans within the included fields refer to the document they were parsed from.
+    pub fn load_includes<'a>(
+        &self,
+        document: HoconValue<'a>,
+        base: impl AsRef<Path>,
+    ) -> Result<HoconValue<'a>, HoconError> {
+        self.loading().expand(document, &[], Base::Directory(base.as_ref()))
+    }
+
+    fn load(&self, origin: Origin) -> Result<HoconValue<'static>, HoconError> {
+        let fields = self.loading().load(origin, self.syntax)?;
+        Ok(HoconValue::HoconObject(fields))
+    }
+
+    fn loading(&self) -> Loading<'_> {
+        Loading {
+            includer: &self.includer,
+            stack: vec![],
+            sources: None,
+        }
+    }
+}
+
+/// Where a document was loaded from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Origin {
+    File(PathBuf),
+    Classpath(String),
+    Url(String),
+}
+
+/// What the includes within a document are resolved against.
+#[derive(Clone, Copy, Debug)]
+enum Base<'o> {
+    /// A document which wasn't loaded by the loader, of which relative files are resolved against the directory.
+    Directory(&'o Path),
+    Document(&'o Origin),
+}
+
+impl Base<'_> {
+    /// The origin of the resource an include within the document refers to.
+    fn resolve(self, resource: &IncludeResource<'_>) -> Origin {
+        match resource {
+            IncludeResource::File(name) => Origin::File(self.file(name)),
+            IncludeResource::Classpath(name) => Origin::Classpath(name.trim_start_matches('/').to_owned()),
+            IncludeResource::Url(url) => Origin::Url(url.to_string()),
+            IncludeResource::Heuristic(name) if has_scheme(name) => Origin::Url(name.to_string()),
+            IncludeResource::Heuristic(name) => match self {
+                Base::Directory(_) | Base::Document(Origin::File(_)) => Origin::File(self.file(name)),
+                Base::Document(Origin::Classpath(current)) => Origin::Classpath(match name.strip_prefix('/') {
+                    Some(absolute) => absolute.to_owned(),
+                    None => format!("{}{name}", &current[..current.rfind('/').map_or(0, |i| i + 1)]),
+                }),
+                Base::Document(Origin::Url(current)) => Origin::Url(relative_url(current, name)),
+            },
+        }
+    }
+
+    /// The path of a file relative to the document, or to the working directory for documents which aren't files.
+    fn file(self, name: &str) -> PathBuf {
+        match self {
+            Base::Directory(dir) => dir.join(name),
+            Base::Document(Origin::File(path)) => path.parent().unwrap_or(Path::new("")).join(name),
+            Base::Document(_) => PathBuf::from(name),
+        }
+    }
+}
+
+impl Origin {
+    /// The syntax inferred from the extension of the name.
+    fn syntax(&self) -> Option<ConfigSyntax> {
+        let extension = match self {
+            Origin::File(path) => path.extension(),
+            Origin::Classpath(name) | Origin::Url(name) => Path::new(name).extension(),
+        };
+        ConfigSyntax::from_extension(extension?.to_str()?)
+    }
+
+    fn with_extension(&self, extension: &str) -> Origin {
+        match self {
+            Origin::File(path) => {
+                let mut path = path.clone().into_os_string();
+                path.push(".");
+                path.push(extension);
+                Origin::File(path.into())
+            }
+            Origin::Classpath(name) => Origin::Classpath(format!("{name}.{extension}")),
+            Origin::Url(url) => Origin::Url(format!("{url}.{extension}")),
+        }
+    }
+
+    fn read(&self, includer: &dyn Includer) -> io::Result<String> {
+        match self {
+            Origin::File(path) => includer.include_file(path),
+            Origin::Classpath(name) => includer.include_classpath(name),
+            Origin::Url(url) => includer.include_url(url),
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::File(path) => write!(f, "{}", path.display()),
+            Origin::Classpath(name) => write!(f, "classpath:{name}"),
+            Origin::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+/// Whether the name starts with a url scheme such as `https://`.
+fn has_scheme(name: &str) -> bool {
+    name.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Resolves a url relative to another url, where a name starting with a slash is relative to the host.
+fn relative_url(base: &str, name: &str) -> String {
+    let authority = base.find("://").map_or(0, |i| i + 3);
+    let end = if name.starts_with('/') {
+        base[authority..].find('/').map_or(base.len(), |i| authority + i)
+    } else {
+        match base[authority..].rfind('/') {
+            Some(i) => authority + i + 1,
+            None => return format!("{base}/{name}"),
+        }
+    };
+    format!("{}{name}", &base[..end])
+}
+
+/// The state of a single load, which tracks the documents being loaded to detect include cycles.
+struct Loading<'i> {
+    includer: &'i dyn Includer,
+    stack: Vec<Origin>,
+    /// The documents loaded so far, when their spans are moved apart to tell them apart.
+    sources: Option<SourceMap>,
+}
+
+impl Loading<'_> {
+    /// Loads the fields of the documents the origin refers to, trying each extension when its name has no known one.
+    fn load(&mut self, origin: Origin, syntax: Option<ConfigSyntax>) -> Result<Vec<HoconField<'static>>, HoconError> {
+        if let Some(syntax) = syntax.or_else(|| origin.syntax()) {
+            return self.load_document(origin, syntax);
+        }
+
+        // Later documents take precedence, as their fields are merged over the ones before them.
+        let mut fields = vec![];
+        let mut found = false;
+        for syntax in [ConfigSyntax::Properties, ConfigSyntax::Json, ConfigSyntax::Conf] {
+            match self.load_document(origin.with_extension(syntax.extension()), syntax) {
+                Ok(document) => {
+                    fields.extend(document);
+                    found = true;
+                }
+                Err(HoconError::Io { error, .. }) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+        }
+        match found {
+            true => Ok(fields),
+            false => self.load_document(origin, ConfigSyntax::Conf),
+        }
+    }
+
+    fn load_document(&mut self, origin: Origin, syntax: ConfigSyntax) -> Result<Vec<HoconField<'static>>, HoconError> {
+        let origin = match origin {
+            Origin::File(path) => Origin::File(path.canonicalize().unwrap_or(path)),
+            origin => origin,
+        };
+        if let Some(start) = self.stack.iter().position(|loading| *loading == origin) {
+            let chain = self.stack[start..]
+                .iter()
+                .chain([&origin])
+                .map(ToString::to_string)
+                .collect();
+            return Err(HoconError::IncludeCycle { chain });
+        }
+
+        // Errors located within an included document are wrapped, so their spans aren't mistaken for ones in the root.
+        let included = !self.stack.is_empty();
+        let in_file = |error| match error {
+            HoconError::ParseError(_) | HoconError::RootArray { .. } | HoconError::IncludeNotFound { .. }
+                if included =>
+            {
+                HoconError::IncludedFile {
+                    path: origin.to_string(),
+                    error: Box::new(error),
+                }
+            }
+            error => error,
+        };
+
+        let content = origin.read(self.includer).map_err(|e| HoconError::Io {
+            path: origin.to_string(),
+            error: e.into(),
+        })?;
+        let document = match syntax {
+            ConfigSyntax::Properties => parse_properties(&content),
+            syntax => parse_with_syntax(&content, syntax),
+        };
+        let mut fields: Vec<_> = match document.map_err(in_file)? {
+            HoconValue::HoconObject(fields) => fields.into_iter().map(HoconField::into_owned).collect(),
+            _ => {
+                let start = content.len() - content.trim_start().len();
+                let span = Span {
+                    start,
+                    end: content.trim_end().len(),
+                };
+                return Err(in_file(HoconError::RootArray { span }));
+            }
+        };
+        let offset = match &mut self.sources {
+            Some(sources) => sources.add(Some(origin.to_string()), content),
+            None => 0,
+        };
+        fields.iter_mut().for_each(|field| shift_field(field, offset));
+
+        self.stack.push(origin.clone());
+        let loaded = self.expand_fields(fields, &[], Base::Document(&origin));
+        self.stack.pop();
+        // The spans of errors are reported within the document itself, rather than at the offset it was moved to.
+        loaded
+            .map_err(|error| match error {
+                HoconError::IncludeNotFound { name, span } => HoconError::IncludeNotFound {
+                    name,
+                    span: Span {
+                        start: span.start - offset,
+                        end: span.end - offset,
+                    },
+                },
+                error => error,
+            })
+            .map_err(in_file)
+    }
+
+    /// Replaces the includes within the value, of which `prefix` is the path from the root of the document.
+    fn expand<'a>(
+        &mut self,
+        value: HoconValue<'a>,
+        prefix: &[Cow<'a, str>],
+        base: Base<'_>,
+    ) -> Result<HoconValue<'a>, HoconError> {
+        match value {
+            HoconValue::HoconObject(fields) => Ok(HoconValue::HoconObject(self.expand_fields(fields, prefix, base)?)),
+            HoconValue::HoconArray(values) => Ok(HoconValue::HoconArray(self.expand_all(values, prefix, base)?)),
+            HoconValue::Concatenation(values) => Ok(HoconValue::Concatenation(self.expand_all(values, prefix, base)?)),
+            value => Ok(value),
+        }
+    }
+
+    fn expand_fields<'a>(
+        &mut self,
+        fields: Vec<HoconField<'a>>,
+        prefix: &[Cow<'a, str>],
+        base: Base<'_>,
+    ) -> Result<Vec<HoconField<'a>>, HoconError> {
+        let mut expanded = vec![];
+        for field in fields {
+            match field {
+                HoconField::KeyValue(path, value) => {
+                    let value = self.expand_spanned(value, &join(prefix, &path), base)?;
+                    expanded.push(HoconField::KeyValue(path, value));
+                }
+                HoconField::Append(path, value, span) => {
+                    let value = self.expand_spanned(value, &join(prefix, &path), base)?;
+                    expanded.push(HoconField::Append(path, value, span));
+                }
+                HoconField::Include(inclusion) => {
+                    expanded.extend(self.include(inclusion.value, inclusion.span, prefix, base)?);
+                }
+            }
+        }
+        Ok(expanded)
+    }
+
+    fn expand_spanned<'a>(
+        &mut self,
+        value: Spanned<HoconValue<'a>>,
+        prefix: &[Cow<'a, str>],
+        base: Base<'_>,
+    ) -> Result<Spanned<HoconValue<'a>>, HoconError> {
+        let expanded = match value.value {
+            // An include used as a value, such as `a = include file("a.conf")`, becomes the object of its fields.
+            HoconValue::HoconInclude(inclusion) => {
+                HoconValue::HoconObject(self.include(inclusion, value.span, prefix, base)?)
+            }
+            value => self.expand(value, prefix, base)?,
+        };
+        Ok(Spanned::new(expanded, value.span))
+    }
+
+    /// Loads the fields of the included resource, made relative to the object at `prefix`. A resource which doesn't
+    /// exist has no fields, unless the include is required.
+    fn include<'a>(
+        &mut self,
+        inclusion: HoconInclusion<'_>,
+        span: Span,
+        prefix: &[Cow<'a, str>],
+        base: Base<'_>,
+    ) -> Result<Vec<HoconField<'a>>, HoconError> {
+        let HoconInclusion { resource, required } = inclusion;
+        match self.load(base.resolve(&resource), None) {
+            Ok(included) => Ok(relativize(included, prefix)),
+            Err(HoconError::Io { error, .. }) if error.kind() == io::ErrorKind::NotFound => match required {
+                true => Err(HoconError::IncludeNotFound {
+                    name: resource.name().to_owned(),
+                    span,
+                }),
+                false => Ok(vec![]),
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    fn expand_all<'a>(
+        &mut self,
+        values: Vec<Spanned<HoconValue<'a>>>,
+        prefix: &[Cow<'a, str>],
+        base: Base<'_>,
+    ) -> Result<Vec<Spanned<HoconValue<'a>>>, HoconError> {
+        values
+            .into_iter()
+            .map(|value| self.expand_spanned(value, prefix, base))
+            .collect()
+    }
+}
+
+fn join<'a>(prefix: &[Cow<'a, str>], path: &HoconPath<'a>) -> Vec<Cow<'a, str>> {
+    prefix.iter().chain(&path.segments).cloned().collect()
+}
+
+/// Moves all spans within the field forward by the offset of its document in the [`SourceMap`].
+fn shift_field(field: &mut HoconField<'_>, offset: usize) {
+    if offset == 0 {
+        return;
+    }
+    match field {
+        HoconField::Include(inclusion) => inclusion.span = inclusion.span.shifted(offset),
+        HoconField::KeyValue(path, value) => {
+            shift_path(path, offset);
+            shift_value(value, offset);
+        }
+        HoconField::Append(path, value, span) => {
+            shift_path(path, offset);
+            shift_value(value, offset);
+            *span = span.shifted(offset);
+        }
+    }
+}
+
+fn shift_value(value: &mut Spanned<HoconValue<'_>>, offset: usize) {
+    value.span = value.span.shifted(offset);
+    match &mut value.value {
+        HoconValue::HoconObject(fields) => fields.iter_mut().for_each(|field| shift_field(field, offset)),
+        HoconValue::HoconArray(values) | HoconValue::Concatenation(values) => {
+            values.iter_mut().for_each(|value| shift_value(value, offset))
+        }
+        HoconValue::Substitution { path, span, .. } => {
+            shift_path(path, offset);
+            *span = span.shifted(offset);
+        }
+        _ => {}
+    }
+}
+
+fn shift_path(path: &mut HoconPath<'_>, offset: usize) {
+    path.spans.iter_mut().for_each(|span| *span = span.shifted(offset));
+}
+
+/// Prefixes the paths of all substitutions within the fields, as they are moved into the object at the prefix.
+fn relativize<'a>(fields: Vec<HoconField<'a>>, prefix: &[Cow<'a, str>]) -> Vec<HoconField<'a>> {
+    if prefix.is_empty() {
+        return fields;
+    }
+    fields
+        .into_iter()
+        .map(|field| match field {
+            HoconField::KeyValue(path, value) => {
+                HoconField::KeyValue(path, value.map(|value| relativize_value(value, prefix)))
+            }
+            HoconField::Append(path, value, span) => {
+                HoconField::Append(path, value.map(|value| relativize_value(value, prefix)), span)
+            }
+            include => include,
+        })
+        .collect()
+}
+
+fn relativize_value<'a>(value: HoconValue<'a>, prefix: &[Cow<'a, str>]) -> HoconValue<'a> {
+    let relativize_all = |values: Vec<Spanned<HoconValue<'a>>>| {
+        values
+            .into_iter()
+            .map(|value| value.map(|value| relativize_value(value, prefix)))
+            .collect()
+    };
+    match value {
+        HoconValue::HoconObject(fields) => HoconValue::HoconObject(relativize(fields, prefix)),
+        HoconValue::HoconArray(values) => HoconValue::HoconArray(relativize_all(values)),
+        HoconValue::Concatenation(values) => HoconValue::Concatenation(relativize_all(values)),
+        HoconValue::Substitution { path, optional, span } => HoconValue::Substitution {
+            path: HoconPath::new(join(prefix, &path)),
+            optional,
+            span,
+        },
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        parser::{parse, HoconString},
+        resolve::resolve,
+        span::Span,
+    };
+
+    fn files(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn load_resolved(dir: &TempDir, name: &str) -> Result<HoconValue<'static>, HoconError> {
+        resolve(load_file(dir.path().join(name))?)
+    }
+
+    fn field<'a>(key: &'a str, value: HoconValue<'a>) -> HoconField<'a> {
+        HoconField::KeyValue(HoconPath::from([key]), value.into())
+    }
+
+    fn int(value: i64) -> HoconValue<'static> {
+        HoconValue::Int(value, value.to_string().into())
+    }
+
+    fn string(value: &str) -> HoconValue<'_> {
+        HoconValue::HoconString(HoconString::Quoted(value.into()))
+    }
+
+    #[test]
+    fn test_include_file() {
+        let dir = files(&[
+            ("application.conf", "a = 1\ninclude file(\"defaults.conf\")\nc = 3"),
+            ("defaults.conf", "a = 0\nb = 2\nc = 0"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field("a", int(0)), field("b", int(2)), field("c", int(3))]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_relative_to_including_file() {
+        let dir = files(&[
+            ("application.conf", "include file(\"conf/server.conf\")"),
+            ("conf/server.conf", "include file(\"port.conf\")\nhost = localhost"),
+            ("conf/port.conf", "port = 8080"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![
+            field("port", int(8080)),
+            field(
+                "host",
+                HoconValue::HoconString(HoconString::Unqouted("localhost".into())),
+            ),
+        ]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_in_nested_object() {
+        let dir = files(&[
+            (
+                "application.conf",
+                "port = 80\nserver.http { include file(\"http.conf\") }",
+            ),
+            (
+                "http.conf",
+                "port = 8080\nurl = \"http://localhost:\"${port}\ndefault = ${?missing}",
+            ),
+        ]);
+        let expected = HoconValue::HoconObject(vec![
+            field("port", int(80)),
+            field(
+                "server",
+                HoconValue::HoconObject(vec![field(
+                    "http",
+                    HoconValue::HoconObject(vec![
+                        field("port", int(8080)),
+                        field("url", string("http://localhost:8080")),
+                    ]),
+                )]),
+            ),
+        ]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_as_value() {
+        let dir = files(&[
+            (
+                "application.conf",
+                "server = include file(\"http.conf\")\nlist = [include file(\"missing.conf\")]",
+            ),
+            ("http.conf", "port = 8080\nurl = \"http://localhost:\"${port}"),
+            ("required.conf", "a = include required(file(\"missing.conf\"))"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![
+            field(
+                "server",
+                HoconValue::HoconObject(vec![
+                    field("port", int(8080)),
+                    field("url", string("http://localhost:8080")),
+                ]),
+            ),
+            field(
+                "list",
+                HoconValue::HoconArray(vec![HoconValue::HoconObject(vec![]).into()]),
+            ),
+        ]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+
+        let expected = HoconError::IncludeNotFound {
+            name: "missing.conf".to_string(),
+            span: Span { start: 4, end: 42 },
+        };
+        assert_eq!(load_resolved(&dir, "required.conf"), Err(expected));
+    }
+
+    #[test]
+    fn test_include_io_error_keeps_source() {
+        let dir = files(&[
+            ("application.conf", "include required(file(\"conf\"))"),
+            ("conf/a.conf", ""),
+        ]);
+        match load_file(dir.path().join("application.conf")) {
+            Err(error @ HoconError::Io { .. }) => {
+                let source = std::error::Error::source(&error).map(ToString::to_string);
+                let HoconError::Io { error: io_error, .. } = &error else {
+                    unreachable!()
+                };
+                // Reading a directory fails with an error of its own, which the message of the error includes.
+                assert_ne!(io_error.kind(), io::ErrorKind::NotFound);
+                assert_eq!(source, Some(io_error.0.to_string()));
+                assert!(error.to_string().ends_with(&io_error.0.to_string()), "{error}");
+            }
+            other => panic!("Expected an I/O error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_file_with_sources() {
+        let dir = files(&[("a.conf", "x = 1\ninclude \"b.conf\""), ("b.conf", "y = 2")]);
+        let (document, sources) = Loader::default()
+            .load_file_with_sources(dir.path().join("a.conf"))
+            .unwrap();
+        let HoconValue::HoconObject(fields) = document else {
+            panic!("Expected an object, got {document:?}")
+        };
+        let origins: Vec<_> = fields
+            .iter()
+            .map(|field| match field {
+                HoconField::KeyValue(_, value) => sources.origin(Some(value.span)).to_string(),
+                other => panic!("Expected a field, got {other:?}"),
+            })
+            .collect();
+        let dir = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            origins,
+            vec![
+                format!("{}:1:5", dir.join("a.conf").display()),
+                format!("{}:1:5", dir.join("b.conf").display()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_file_with_sources_reports_errors_within_the_document() {
+        let dir = files(&[
+            ("a.conf", "x = 1\ninclude \"b.conf\""),
+            ("b.conf", "include required(\"c.conf\")"),
+        ]);
+        match Loader::default().load_file_with_sources(dir.path().join("a.conf")) {
+            Err(HoconError::IncludedFile { error, .. }) => assert_eq!(
+                *error,
+                HoconError::IncludeNotFound {
+                    name: "c.conf".to_string(),
+                    span: Span { start: 0, end: 26 }
+                }
+            ),
+            other => panic!("Expected an error within b.conf, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_include_self_referential_substitution() {
+        let dir = files(&[
+            ("application.conf", "env { path = /bin, include file(\"extra.conf\") }"),
+            ("extra.conf", "path = ${path}\":/extra\""),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field(
+            "env",
+            HoconValue::HoconObject(vec![field("path", string("/bin:/extra"))]),
+        )]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_append() {
+        let dir = files(&[
+            (
+                "application.conf",
+                "a { list = [1] }\na { include file(\"more.conf\") }\na.list += 3",
+            ),
+            ("more.conf", "list += 2"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field(
+            "a",
+            HoconValue::HoconObject(vec![field(
+                "list",
+                HoconValue::HoconArray(vec![int(1).into(), int(2).into(), int(3).into()]),
+            )]),
+        )]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = files(&[
+            ("a.conf", "include file(\"b.conf\")"),
+            ("b.conf", "x { include file(\"a.conf\") }"),
+        ]);
+        let path = |name: &str| dir.path().canonicalize().unwrap().join(name).display().to_string();
+        let expected = HoconError::IncludeCycle {
+            chain: vec![path("a.conf"), path("b.conf"), path("a.conf")],
+        };
+        assert_eq!(load_file(dir.path().join("a.conf")), Err(expected));
+    }
+
+    #[test]
+    fn test_include_heuristic() {
+        let dir = files(&[
+            ("application.conf", "include \"defaults.conf\"\nb = 2"),
+            ("defaults.conf", "a = 1\nb = 1"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field("a", int(1)), field("b", int(2))]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_missing() {
+        let dir = files(&[(
+            "application.conf",
+            "a = 1\ninclude \"missing.conf\"\nb { include file(\"missing.conf\") }",
+        )]);
+        let expected = HoconValue::HoconObject(vec![field("a", int(1)), field("b", HoconValue::HoconObject(vec![]))]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_required() {
+        let dir = files(&[
+            (
+                "application.conf",
+                "include required(\"defaults.conf\")\ninclude required(file(\"other.conf\"))",
+            ),
+            ("defaults.conf", "a = 1"),
+            ("nested.conf", "include required(\"application.conf\")"),
+        ]);
+        let expected = || HoconError::IncludeNotFound {
+            name: "other.conf".to_string(),
+            span: Span { start: 34, end: 70 },
+        };
+        assert_eq!(load_file(dir.path().join("application.conf")), Err(expected()));
+        match load_file(dir.path().join("nested.conf")) {
+            Err(HoconError::IncludedFile { path, error }) => {
+                assert!(path.ends_with("application.conf"), "{path}");
+                assert_eq!(*error, expected());
+            }
+            other => panic!("Expected an error in the included file, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_include_invalid_file() {
+        let dir = files(&[
+            ("application.conf", "include file(\"broken.conf\")"),
+            ("broken.conf", "a = {"),
+        ]);
+        match load_file(dir.path().join("application.conf")) {
+            Err(HoconError::IncludedFile { path, error }) => {
+                assert!(path.ends_with("broken.conf"), "{path}");
+                assert!(matches!(*error, HoconError::ParseError(_)), "{error:?}");
+            }
+            other => panic!("Expected an error in the included file, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_include_without_extension() {
+        let dir = files(&[
+            ("root.conf", "include \"application\"\nd = 4"),
+            ("application.conf", "a = 1"),
+            ("application.json", "{ \"a\": 2, \"b\": 2 }"),
+            ("application.properties", "a = 3\nb = 3\nc.d = 3"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![
+            field("a", int(1)),
+            field("b", int(2)),
+            field("c", HoconValue::HoconObject(vec![field("d", string("3"))])),
+            field("d", int(4)),
+        ]);
+        assert_eq!(load_resolved(&dir, "root.conf"), Ok(expected));
+
+        let dir = files(&[
+            ("root.conf", "include required(\"application\")"),
+            ("application.properties", "a = 1"),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field("a", string("1"))]);
+        assert_eq!(load_resolved(&dir, "root.conf"), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_without_extension_missing() {
+        let dir = files(&[("root.conf", "include \"application\"\ninclude required(\"other\")")]);
+        let expected = HoconError::IncludeNotFound {
+            name: "other".to_string(),
+            span: Span { start: 22, end: 47 },
+        };
+        assert_eq!(load_resolved(&dir, "root.conf"), Err(expected));
+    }
+
+    #[test]
+    fn test_include_syntax_from_extension() {
+        let dir = files(&[
+            ("application.conf", "include \"a.json\"\ninclude \"b.properties\""),
+            ("a.json", "{ \"a\": [1, 2] }"),
+            ("b.properties", "b.c = x y"),
+            ("list.json", "[1]"),
+            ("root.conf", "include \"list.json\""),
+        ]);
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::HoconArray(vec![int(1).into(), int(2).into()])),
+            field("b", HoconValue::HoconObject(vec![field("c", string("x y"))])),
+        ]);
+        assert_eq!(load_resolved(&dir, "application.conf"), Ok(expected));
+        match load_file(dir.path().join("root.conf")) {
+            Err(HoconError::IncludedFile { path, error }) => {
+                assert!(path.ends_with("list.json"), "{path}");
+                assert_eq!(
+                    *error,
+                    HoconError::RootArray {
+                        span: Span { start: 0, end: 3 }
+                    }
+                );
+            }
+            other => panic!("Expected an error in the included file, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_with_syntax() {
+        let dir = files(&[("settings.txt", "a.b = 1\ninclude = \"c.conf\""), ("c.conf", "c = 1")]);
+        let loaded = Loader::default()
+            .with_syntax(ConfigSyntax::Properties)
+            .load_file(dir.path().join("settings.txt"))
+            .and_then(resolve);
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::HoconObject(vec![field("b", string("1"))])),
+            field("include", string("\"c.conf\"")),
+        ]);
+        assert_eq!(loaded, Ok(expected));
+
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::HoconObject(vec![field("b", int(1))])),
+            field("include", string("c.conf")),
+        ]);
+        assert_eq!(load_resolved(&dir, "settings.txt"), Ok(expected));
+    }
+
+    #[test]
+    fn test_load_includes_from_parsed_document() {
+        let dir = files(&[("defaults.conf", "b = 2")]);
+        let document = parse("a = 1\ninclude file(\"defaults.conf\")").unwrap();
+        let expected = HoconValue::HoconObject(vec![field("a", int(1)), field("b", int(2))]);
+        assert_eq!(load_includes(document, dir.path()), Ok(expected));
+    }
+
+    #[test]
+    fn test_include_classpath() {
+        let dir = files(&[
+            ("application.conf", "include classpath(\"/lib/reference.conf\")\nc = 3"),
+            ("classpath/lib/reference.conf", "include \"defaults.conf\"\nb = 2"),
+        ]);
+        let includer = DefaultIncluder::default()
+            .with_resource("lib/defaults.conf", "a = 1\nb = 1")
+            .with_classpath_dir(dir.path().join("missing"))
+            .with_classpath_dir(dir.path().join("classpath"));
+        let loaded = Loader::new(includer).load_file(dir.path().join("application.conf"));
+        let expected = HoconValue::HoconObject(vec![field("a", int(1)), field("b", int(2)), field("c", int(3))]);
+        assert_eq!(resolve(loaded.unwrap()), Ok(expected));
+    }
+
+    #[test]
+    fn test_load_classpath_resource() {
+        let includer = DefaultIncluder::default()
+            .with_resource("reference.conf", "include \"other.conf\"\na = 1")
+            .with_resource("other.conf", String::from("b = 2"));
+        let loaded = Loader::new(includer).load_classpath("reference.conf");
+        let expected = HoconValue::HoconObject(vec![field("b", int(2)), field("a", int(1))]);
+        assert_eq!(loaded, Ok(expected));
+    }
+
+    #[test]
+    fn test_include_url() {
+        let includer = DefaultIncluder::default().with_url_fetcher(|url| match url {
+            "https://example.com/conf/app.conf" => Ok("include \"db.conf\"\ninclude \"/shared.conf\"".to_string()),
+            "https://example.com/conf/db.conf" => Ok("a = 1".to_string()),
+            "https://example.com/shared.conf" => Ok("b = 2".to_string()),
+            _ => Err(io::ErrorKind::NotFound.into()),
+        });
+        let document = parse("include url(\"https://example.com/conf/app.conf\")\ninclude \"https://example.com/x\"");
+        let loaded = Loader::new(includer).load_includes(document.unwrap(), ".");
+        let expected = HoconValue::HoconObject(vec![field("a", int(1)), field("b", int(2))]);
+        assert_eq!(loaded, Ok(expected));
+    }
+
+    #[test]
+    fn test_include_url_without_fetcher() {
+        let document = parse("include url(\"https://example.com/app.conf\")").unwrap();
+        let expected = HoconError::Io {
+            path: "https://example.com/app.conf".to_string(),
+            error: io::Error::from(io::ErrorKind::Unsupported).into(),
+        };
+        assert_eq!(load_includes(document, "."), Err(expected));
+    }
+
+    #[test]
+    fn test_custom_includer() {
+        struct InMemory(HashMap<PathBuf, &'static str>);
+
+        impl Includer for InMemory {
+            fn include_file(&self, path: &Path) -> io::Result<String> {
+                self.0
+                    .get(path)
+                    .map(|content| content.to_string())
+                    .ok_or(io::ErrorKind::NotFound.into())
+            }
+        }
+
+        let includer = InMemory(HashMap::from([
+            (PathBuf::from("conf/app.conf"), "include \"db.conf\"\nport = 80"),
+            (PathBuf::from("conf/db.conf"), "include required(classpath(\"x.conf\"))"),
+        ]));
+        let loaded = Loader::new(includer).load_file("conf/app.conf");
+        let expected = HoconError::Io {
+            path: "classpath:x.conf".to_string(),
+            error: io::Error::from(io::ErrorKind::Unsupported).into(),
+        };
+        assert_eq!(loaded, Err(expected));
+    }
+
+    #[test]
+    fn test_relative_url() {
+        assert_eq!(
+            relative_url("https://a.com/b/c.conf", "d.conf"),
+            "https://a.com/b/d.conf"
+        );
+        assert_eq!(
+            relative_url("https://a.com/b/c.conf", "/d.conf"),
+            "https://a.com/d.conf"
+        );
+        assert_eq!(relative_url("https://a.com", "d.conf"), "https://a.com/d.conf");
+        assert_eq!(relative_url("https://a.com", "/d.conf"), "https://a.com/d.conf");
+    }
+}
@@ -1,4 +1,11 @@
+pub mod config;
+pub mod include;
+pub mod merge;
 pub mod parser;
+pub mod properties;
+pub mod resolve;
+pub mod span;
+pub mod syntax;
 
 #[cfg(feature = "serde")]
 pub mod serde;
@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+
+use crate::{
+    parser::{HoconField, HoconPath, HoconValue},
+    span::{Span, Spanned},
+};
+
+/// Expands the path expressions used as keys into nested objects.
+///
+/// A field such as `a.b.c = 1` becomes `a { b { c = 1 } }`. When an object field shares its key with an earlier
+/// object field in the same object, the two are merged into the earlier field. Fields using `+=` are rewritten to
+/// their equivalent `a = ${?a} [value]`, using the full path of the field from the root. Array elements have no path
+/// of their own, so the fields of objects within arrays are expanded as if the object was the root.
+pub fn expand_paths(value: HoconValue<'_>) -> HoconValue<'_> {
+    expand_value(value, &[])
+}
+
+fn expand_value<'a>(value: HoconValue<'a>, prefix: &[Cow<'a, str>]) -> HoconValue<'a> {
+    let expand = |values: Vec<Spanned<HoconValue<'a>>>, prefix: &[Cow<'a, str>]| {
+        values
+            .into_iter()
+            .map(|value| Spanned::new(expand_value(value.value, prefix), value.span))
+            .collect()
+    };
+    match value {
+        HoconValue::HoconObject(fields) => HoconValue::HoconObject(expand_fields(fields, prefix)),
+        HoconValue::HoconArray(values) => HoconValue::HoconArray(expand(values, &[])),
+        HoconValue::Concatenation(values) => HoconValue::Concatenation(expand(values, prefix)),
+        value => value,
+    }
+}
+
+fn expand_fields<'a>(fields: Vec<HoconField<'a>>, prefix: &[Cow<'a, str>]) -> Vec<HoconField<'a>> {
+    let mut expanded = vec![];
+    for field in fields {
+        let (path, value) = match field {
+            HoconField::KeyValue(path, value) => (path, value),
+            HoconField::Append(path, value, span) => {
+                let previous = HoconValue::Substitution {
+                    path: HoconPath::new(prefix.iter().chain(&path.segments).cloned().collect()),
+                    optional: true,
+                    span,
+                };
+                let full_span = span.to(value.span);
+                let appended = Spanned::new(HoconValue::HoconArray(vec![value]), full_span);
+                let value = HoconValue::Concatenation(vec![Spanned::new(previous, span), appended]);
+                (path, Spanned::new(value, full_span))
+            }
+            include => {
+                expanded.push(include);
+                continue;
+            }
+        };
+
+        let full_path: Vec<_> = prefix.iter().chain(&path.segments).cloned().collect();
+        let value = Spanned::new(expand_value(value.value, &full_path), value.span);
+        let mut keys = path
+            .segments
+            .into_iter()
+            .enumerate()
+            .map(|(i, segment)| single_key(segment, path.spans.get(i).copied()));
+        let Some(key) = keys.next() else {
+            continue;
+        };
+        let value = keys.rev().fold(value, |value, key| {
+            let span = key.span().map_or(value.span, |span| span.to(value.span));
+            Spanned::new(HoconValue::HoconObject(vec![HoconField::KeyValue(key, value)]), span)
+        });
+        insert(&mut expanded, key, value);
+    }
+    expanded
+}
+
+fn single_key(key: Cow<'_, str>, span: Option<Span>) -> HoconPath<'_> {
+    HoconPath {
+        segments: vec![key],
+        spans: span.into_iter().collect(),
+    }
+}
+
+/// Inserts an already expanded value into the fields, merging it into the last field with the same key when both
+/// are objects.
+fn insert<'a>(fields: &mut Vec<HoconField<'a>>, key: HoconPath<'a>, value: Spanned<HoconValue<'a>>) {
+    let existing = fields.iter_mut().rev().find_map(|field| match field {
+        HoconField::KeyValue(path, existing) if *path == key => Some(existing),
+        _ => None,
+    });
+
+    match (existing.map(|existing| &mut existing.value), value.value) {
+        (Some(HoconValue::HoconObject(existing)), HoconValue::HoconObject(new_fields)) => {
+            for field in new_fields {
+                match field {
+                    HoconField::KeyValue(path, value) => insert(existing, path, value),
+                    other => existing.push(other),
+                }
+            }
+        }
+        (_, new_value) => fields.push(HoconField::KeyValue(key, Spanned::new(new_value, value.span))),
+    }
+}
+
+/// Merges a resolved field into the fields of a resolved object, following the rules for duplicate keys.
+///
+/// When both the existing and the new value are objects they are merged recursively. Otherwise the new value
+/// replaces the existing one, which also means a later non-object value resets an earlier object.
+pub(crate) fn merge_field<'a>(fields: &mut Vec<HoconField<'a>>, key: HoconPath<'a>, value: Spanned<HoconValue<'a>>) {
+    let existing = fields.iter_mut().find_map(|field| match field {
+        HoconField::KeyValue(path, existing) if *path == key => Some(existing),
+        _ => None,
+    });
+
+    match existing {
+        Some(existing) => match (&mut existing.value, value.value) {
+            (HoconValue::HoconObject(existing), HoconValue::HoconObject(new_fields)) => {
+                merge_objects(existing, new_fields)
+            }
+            (_, new_value) => *existing = Spanned::new(new_value, value.span),
+        },
+        None => fields.push(HoconField::KeyValue(key, value)),
+    }
+}
+
+/// Merges the fields of a resolved object into another, as if they had been written after its fields.
+pub(crate) fn merge_objects<'a>(fields: &mut Vec<HoconField<'a>>, new_fields: Vec<HoconField<'a>>) {
+    for field in new_fields {
+        match field {
+            HoconField::KeyValue(key, value) => merge_field(fields, key, value),
+            other => fields.push(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{parse, HoconString},
+        span::Span,
+    };
+
+    fn parse_expanded(input: &str) -> HoconValue<'_> {
+        expand_paths(parse(input).unwrap())
+    }
+
+    #[test]
+    fn test_expand_dotted_path() {
+        let expected = HoconValue::HoconObject(vec![HoconField::KeyValue(
+            HoconPath::from(["a"]),
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["b"]),
+                HoconValue::HoconObject(vec![HoconField::KeyValue(
+                    HoconPath::from(["c"]),
+                    HoconValue::Int(1, "1".into()).into(),
+                )])
+                .into(),
+            )])
+            .into(),
+        )]);
+        assert_eq!(parse_expanded("a.b.c = 1"), expected);
+    }
+
+    #[test]
+    fn test_expand_merges_with_siblings() {
+        let content = r#"
+            a.b = 1
+            other = true
+            a { c = 2 }
+            a.d.e = 3
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            HoconField::KeyValue(
+                HoconPath::from(["a"]),
+                HoconValue::HoconObject(vec![
+                    HoconField::KeyValue(HoconPath::from(["b"]), HoconValue::Int(1, "1".into()).into()),
+                    HoconField::KeyValue(HoconPath::from(["c"]), HoconValue::Int(2, "2".into()).into()),
+                    HoconField::KeyValue(
+                        HoconPath::from(["d"]),
+                        HoconValue::HoconObject(vec![HoconField::KeyValue(
+                            HoconPath::from(["e"]),
+                            HoconValue::Int(3, "3".into()).into(),
+                        )])
+                        .into(),
+                    ),
+                ])
+                .into(),
+            ),
+            HoconField::KeyValue(HoconPath::from(["other"]), HoconValue::HoconBoolean(true).into()),
+        ]);
+        assert_eq!(parse_expanded(content), expected);
+    }
+
+    #[test]
+    fn test_expand_quoted_segment() {
+        let expected = HoconValue::HoconObject(vec![HoconField::KeyValue(
+            HoconPath::from(["a"]),
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["b.c"]),
+                HoconValue::HoconString(HoconString::Unqouted("x".into())).into(),
+            )])
+            .into(),
+        )]);
+        assert_eq!(parse_expanded(r#"a."b.c" = x"#), expected);
+    }
+
+    #[test]
+    fn test_expand_inside_arrays() {
+        let expected = HoconValue::HoconObject(vec![HoconField::KeyValue(
+            HoconPath::from(["list"]),
+            HoconValue::HoconArray(vec![HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["a"]),
+                HoconValue::HoconObject(vec![HoconField::KeyValue(
+                    HoconPath::from(["b"]),
+                    HoconValue::Int(1, "1".into()).into(),
+                )])
+                .into(),
+            )])
+            .into()])
+            .into(),
+        )]);
+        assert_eq!(parse_expanded("list = [ { a.b = 1 } ]"), expected);
+    }
+
+    #[test]
+    fn test_expand_append_uses_full_path() {
+        let expected = HoconValue::HoconObject(vec![HoconField::KeyValue(
+            HoconPath::from(["a"]),
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["b"]),
+                HoconValue::Concatenation(vec![
+                    HoconValue::Substitution {
+                        path: HoconPath::from(["a", "b"]),
+                        optional: true,
+                        span: Span { start: 6, end: 8 },
+                    }
+                    .into(),
+                    HoconValue::HoconArray(vec![HoconValue::Int(1, "1".into()).into()]).into(),
+                ])
+                .into(),
+            )])
+            .into(),
+        )]);
+        assert_eq!(parse_expanded("a { b += 1 }"), expected);
+    }
+
+    #[test]
+    fn test_expand_append_inside_arrays_uses_element_path() {
+        let expected = HoconValue::HoconObject(vec![HoconField::KeyValue(
+            HoconPath::from(["list"]),
+            HoconValue::HoconArray(vec![HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["a"]),
+                HoconValue::Concatenation(vec![
+                    HoconValue::Substitution {
+                        path: HoconPath::from(["a"]),
+                        optional: true,
+                        span: Span { start: 13, end: 15 },
+                    }
+                    .into(),
+                    HoconValue::HoconArray(vec![HoconValue::Int(1, "1".into()).into()]).into(),
+                ])
+                .into(),
+            )])
+            .into()])
+            .into(),
+        )]);
+        assert_eq!(parse_expanded("list = [ { a += 1 } ]"), expected);
+    }
+}
@@ -1,494 +1,2523 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, cell::RefCell, cmp::Ordering, collections::HashSet, fmt, rc::Rc, str::FromStr};
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_till1, take_while},
-    character::{anychar, complete::char},
-    combinator::{all_consuming, map, not, opt, peek, recognize, value, verify},
-    error::ParseError,
-    multi::{many0, many1, separated_list0},
-    number::complete::double,
-    sequence::{delimited, preceded, terminated},
-    AsChar, IResult, Input, Parser,
+    bytes::complete::{tag, take_till, take_till1, take_while, take_while1, take_while_m_n},
+    character::{
+        anychar,
+        complete::{char, digit1, one_of},
+    },
+    combinator::{all_consuming, consumed, map, map_opt, not, opt, peek, recognize, value, verify},
+    error::{ErrorKind, FromExternalError, ParseError},
+    multi::{fold_many0, many0, many0_count, many1},
+    sequence::{delimited, preceded},
+    IResult, Input as _, Parser,
 };
-use nom_language::error::convert_error;
+use nom_locate::LocatedSpan;
 use thiserror::Error;
 
+use crate::span::{ColumnUnit, Position, Span, Spanned};
+
+/// The parser input, tracking the byte offset of each fragment within the document. When parsing with
+/// [`parse_recovering`] it also carries the errors which were recovered from.
+pub(crate) type Input<'a> = LocatedSpan<&'a str, Option<Recovered>>;
+
+/// The errors the parser recovered from so far, shared between all fragments of the input.
+pub(crate) type Recovered = Rc<RefCell<Vec<DocumentError>>>;
+
+impl Span {
+    fn of(input: Input<'_>) -> Self {
+        let start = input.location_offset();
+        Span {
+            start,
+            end: start + input.fragment().len(),
+        }
+    }
+}
+
+/// An include such as `include "name"` or `include required(file("name"))`.
 #[derive(Clone, Debug, PartialEq)]
-pub struct HoconObject<'a> {
-    pub data: HashMap<String, HoconValue<'a>>,
+pub struct HoconInclusion<'a> {
+    pub resource: IncludeResource<'a>,
+    /// Whether the include is wrapped in `required(...)`, which makes it an error when the resource can't be found.
+    /// Other includes of a missing resource are ignored.
+    pub required: bool,
+}
+
+impl HoconInclusion<'_> {
+    /// Copies all borrowed text, detaching the inclusion from the document it was parsed from.
+    pub fn into_owned(self) -> HoconInclusion<'static> {
+        HoconInclusion {
+            resource: self.resource.into_owned(),
+            required: self.required,
+        }
+    }
+}
+
+impl<'a> From<IncludeResource<'a>> for HoconInclusion<'a> {
+    /// An include of the resource which isn't required.
+    fn from(resource: IncludeResource<'a>) -> Self {
+        HoconInclusion {
+            resource,
+            required: false,
+        }
+    }
 }
 
+/// The resource an include refers to.
 #[derive(Clone, Debug, PartialEq)]
-pub enum HoconInclusion<'a> {
-    File(&'a str),
-    Url(&'a str),
-    Classpath(&'a str),
+pub enum IncludeResource<'a> {
+    /// `include "name"`, of which the kind of resource depends on where the including document was loaded from.
+    Heuristic(Cow<'a, str>),
+    File(Cow<'a, str>),
+    Url(Cow<'a, str>),
+    Classpath(Cow<'a, str>),
+}
+
+impl IncludeResource<'_> {
+    /// Copies the borrowed name, detaching the resource from the document it was parsed from.
+    pub fn into_owned(self) -> IncludeResource<'static> {
+        match self {
+            IncludeResource::Heuristic(name) => IncludeResource::Heuristic(owned(name)),
+            IncludeResource::File(name) => IncludeResource::File(owned(name)),
+            IncludeResource::Url(name) => IncludeResource::Url(owned(name)),
+            IncludeResource::Classpath(name) => IncludeResource::Classpath(owned(name)),
+        }
+    }
+
+    /// The name of the resource as written in the document.
+    pub fn name(&self) -> &str {
+        match self {
+            IncludeResource::Heuristic(name)
+            | IncludeResource::File(name)
+            | IncludeResource::Url(name)
+            | IncludeResource::Classpath(name) => name,
+        }
+    }
+}
+
+fn owned(value: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(value.into_owned())
+}
+
+/// A path expression such as `a."b.c".d`, split into the individual keys it consists of.
+///
+/// Like [`Spanned`] nodes, paths are compared by their segments only.
+#[derive(Clone, Debug, Default)]
+pub struct HoconPath<'a> {
+    pub segments: Vec<Cow<'a, str>>,
+    /// The location of each segment within the document, empty when the path wasn't parsed from a document.
+    pub spans: Vec<Span>,
+}
+
+impl<'a> HoconPath<'a> {
+    pub fn new(segments: Vec<Cow<'a, str>>) -> Self {
+        HoconPath {
+            segments,
+            spans: vec![],
+        }
+    }
+
+    /// The location of the whole path within the document.
+    pub fn span(&self) -> Option<Span> {
+        Some(self.spans.first()?.to(*self.spans.last()?))
+    }
+
+    /// Copies all borrowed segments, detaching the path from the document it was parsed from.
+    pub fn into_owned(self) -> HoconPath<'static> {
+        HoconPath {
+            segments: self.segments.into_iter().map(owned).collect(),
+            spans: self.spans,
+        }
+    }
+}
+
+impl PartialEq for HoconPath<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.segments == other.segments
+    }
+}
+
+impl<'a, const N: usize> From<[&'a str; N]> for HoconPath<'a> {
+    fn from(segments: [&'a str; N]) -> Self {
+        HoconPath::new(segments.into_iter().map(Cow::Borrowed).collect())
+    }
+}
+
+impl fmt::Display for HoconPath<'_> {
+    /// Renders the path in its dotted form, quoting the segments which can't be written as unquoted strings.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            let needs_quotes = segment.is_empty()
+                || segment.contains("//")
+                || segment.chars().any(|c| c == '.' || is_forbidden_unquoted_char(c));
+            if needs_quotes {
+                write_quoted(f, segment)?;
+            } else {
+                f.write_str(segment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the given string as a JSON compatible quoted string.
+fn write_quoted(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum HoconField<'a> {
-    Include(HoconInclusion<'a>),
-    KeyValue(&'a str, HoconValue<'a>),
+    Include(Spanned<HoconInclusion<'a>>),
+    KeyValue(HoconPath<'a>, Spanned<HoconValue<'a>>),
+    /// `path += value`, which appends the value to the array previously assigned to the path. The span covers the
+    /// `+=` operator.
+    Append(HoconPath<'a>, Spanned<HoconValue<'a>>, Span),
+}
+
+impl HoconField<'_> {
+    /// Copies all borrowed text, detaching the field from the document it was parsed from.
+    pub fn into_owned(self) -> HoconField<'static> {
+        match self {
+            HoconField::Include(inclusion) => HoconField::Include(inclusion.map(HoconInclusion::into_owned)),
+            HoconField::KeyValue(path, value) => {
+                HoconField::KeyValue(path.into_owned(), value.map(HoconValue::into_owned))
+            }
+            HoconField::Append(path, value, span) => {
+                HoconField::Append(path.into_owned(), value.map(HoconValue::into_owned), span)
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum HoconString<'a> {
-    Quoted(&'a str),
-    Unqouted(&'a str),
+    Quoted(Cow<'a, str>),
+    Unqouted(Cow<'a, str>),
+    /// A triple quoted string, taken verbatim without any escape processing.
+    Multiline(Cow<'a, str>),
+}
+
+impl HoconString<'_> {
+    /// Copies the borrowed text, detaching the string from the document it was parsed from.
+    pub fn into_owned(self) -> HoconString<'static> {
+        match self {
+            HoconString::Quoted(value) => HoconString::Quoted(owned(value)),
+            HoconString::Unqouted(value) => HoconString::Unqouted(owned(value)),
+            HoconString::Multiline(value) => HoconString::Multiline(owned(value)),
+        }
+    }
 }
 
 /// Represents a hocon value within the AST representation.
 #[derive(Clone, Debug, PartialEq)]
 pub enum HoconValue<'a> {
     HoconString(HoconString<'a>),
-    HoconNumber(f64),
+    /// An integer which fits into an `i64`, along with its literal text.
+    Int(i64, Cow<'a, str>),
+    /// A number with a fraction or exponent, along with its literal text.
+    Float(f64, Cow<'a, str>),
+    /// An integer which doesn't fit into an `i64`, kept as its literal text.
+    BigInt(Cow<'a, str>),
     HoconObject(Vec<HoconField<'a>>),
-    HoconArray(Vec<HoconValue<'a>>),
+    HoconArray(Vec<Spanned<HoconValue<'a>>>),
     HoconBoolean(bool),
     HoconNull,
     HoconInclude(HoconInclusion<'a>),
+    /// A reference to another value in the document, `${path}` or `${?path}` when optional.
+    Substitution {
+        path: HoconPath<'a>,
+        optional: bool,
+        span: Span,
+    },
+    /// Adjacent values on a single line which are joined together, including the whitespace between them.
+    Concatenation(Vec<Spanned<HoconValue<'a>>>),
+}
+
+impl HoconValue<'_> {
+    /// Copies all borrowed text, detaching the value from the document it was parsed from so it can outlive it.
+    pub fn into_owned(self) -> HoconValue<'static> {
+        let values = |values: Vec<Spanned<HoconValue<'_>>>| {
+            values
+                .into_iter()
+                .map(|value| value.map(HoconValue::into_owned))
+                .collect()
+        };
+        match self {
+            HoconValue::HoconString(value) => HoconValue::HoconString(value.into_owned()),
+            HoconValue::Int(value, literal) => HoconValue::Int(value, owned(literal)),
+            HoconValue::Float(value, literal) => HoconValue::Float(value, owned(literal)),
+            HoconValue::BigInt(literal) => HoconValue::BigInt(owned(literal)),
+            HoconValue::HoconObject(fields) => {
+                HoconValue::HoconObject(fields.into_iter().map(HoconField::into_owned).collect())
+            }
+            HoconValue::HoconArray(elements) => HoconValue::HoconArray(values(elements)),
+            HoconValue::HoconBoolean(value) => HoconValue::HoconBoolean(value),
+            HoconValue::HoconNull => HoconValue::HoconNull,
+            HoconValue::HoconInclude(inclusion) => HoconValue::HoconInclude(inclusion.into_owned()),
+            HoconValue::Substitution { path, optional, span } => HoconValue::Substitution {
+                path: path.into_owned(),
+                optional,
+                span,
+            },
+            HoconValue::Concatenation(elements) => HoconValue::Concatenation(values(elements)),
+        }
+    }
+}
+
+impl FromStr for HoconValue<'static> {
+    type Err = HoconError;
+
+    /// Parses a Hocon document into an AST which owns all of its text, such that it doesn't borrow from the input.
+    fn from_str(input: &str) -> Result<Self, HoconError> {
+        parse(input).map(HoconValue::into_owned)
+    }
 }
 
 /// Represents the various modes of failure while parsing or evaluating hocon files.
 #[derive(Error, Debug, PartialEq)]
 pub enum HoconError {
-    // TODO Integrate better with nom error to get better parsing error docs
-    #[error("Parse error")]
-    ParseError { msg: String },
+    #[error("Parse error at line {}, column {}: {}", .0.position.line + 1, .0.position.column + 1, .0)]
+    ParseError(#[from] SyntaxError),
+    #[error("{msg}")]
+    DeserializeError { msg: String },
+    #[error("Could not resolve substitution ${{{path}}}")]
+    UnresolvedSubstitution { path: String, span: Span },
+    #[error("Substitution cycle: {}", chain.join(" -> "))]
+    SubstitutionCycle { chain: Vec<String> },
+    #[error("Cannot concatenate {found} with {expected} at {path}")]
+    InvalidConcatenation {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("The root of a HOCON document must be an object, arrays are only allowed at the root of JSON documents")]
+    RootArray { span: Span },
+    #[error("Could not read {path}: {error}")]
+    Io {
+        path: String,
+        #[source]
+        error: IoError,
+    },
+    #[error("Include cycle: {}", chain.join(" -> "))]
+    IncludeCycle { chain: Vec<String> },
+    #[error("Could not find required include {name}")]
+    IncludeNotFound { name: String, span: Span },
+    /// An error within a file which was included by the document, of which any spans refer to the included file.
+    #[error("In included file {path}: {error}")]
+    IncludedFile { path: String, error: Box<HoconError> },
+    /// A document in a syntax which the parser doesn't handle, such as Java properties given to
+    /// [`parse_with_syntax`].
+    #[error("Documents in the .{} syntax are not supported here", syntax.extension())]
+    UnsupportedSyntax { syntax: ConfigSyntax },
+}
+
+/// The I/O error a document could not be read with, which is equal to other errors of the same kind such that
+/// [`HoconError`] can still be compared.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct IoError(#[from] pub std::io::Error);
+
+impl IoError {
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.0.kind()
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind()
+    }
+}
+
+/// The reason a document could not be parsed.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    #[error("Unexpected character {0:?}")]
+    UnexpectedChar(char),
+    #[error("Unexpected end of input")]
+    UnexpectedEnd,
+    #[error("Unterminated string")]
+    UnterminatedString,
+    #[error("Invalid escape sequence")]
+    InvalidEscape,
+    /// Two fields or array elements which are neither separated by a comma nor by a newline.
+    #[error("Missing separator")]
+    MissingSeparator,
+    #[error("Missing key")]
+    MissingKey,
+    /// A key which isn't followed by `:`, `=`, `+=` or an object.
+    #[error("Missing separator after key")]
+    MissingKeySeparator,
+    #[error("Missing value")]
+    MissingValue,
+}
+
+/// A detailed description of why and where a document could not be parsed.
+#[derive(Error, Clone, Debug, PartialEq)]
+#[error("{kind}{}", format_expected(expected))]
+pub struct SyntaxError {
+    pub kind: SyntaxErrorKind,
+    /// The location of the offending input, empty when the input ended unexpectedly.
+    pub span: Span,
+    /// The zero based line and column (in bytes) of the start of the span.
+    pub position: Position,
+    /// The tokens which would have been valid at the location of the error.
+    pub expected: Vec<String>,
+    /// The line of the document on which the error occurred.
+    pub snippet: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(source: &str, offset: usize, kind: Option<SyntaxErrorKind>, expected: Vec<String>) -> Self {
+        let next = source[offset..].chars().next();
+        let kind = kind.unwrap_or(match next {
+            Some(c) => SyntaxErrorKind::UnexpectedChar(c),
+            None => SyntaxErrorKind::UnexpectedEnd,
+        });
+        let line_start = source[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |newline| offset + newline);
+        SyntaxError {
+            kind,
+            span: Span {
+                start: offset,
+                end: offset + next.map_or(0, char::len_utf8),
+            },
+            position: Position::from_offset(source, offset, ColumnUnit::Utf8),
+            expected,
+            snippet: source[line_start..line_end].trim_end_matches('\r').to_string(),
+        }
+    }
+}
+
+fn format_expected(expected: &[String]) -> String {
+    match expected {
+        [] => String::new(),
+        [token] => format!(", expected {token}"),
+        [tokens @ .., last] => format!(", expected {} or {last}", tokens.join(", ")),
+    }
+}
+
+/// Describes a character the parser expected in an error message.
+fn describe_char(c: char) -> String {
+    match c {
+        '\n' => "newline".to_string(),
+        c => format!("{c:?}"),
+    }
+}
+
+/// The parsers report errors through nom's [`ParseError`], and the failures specific to HOCON as an external error
+/// carrying the [`SyntaxErrorKind`].
+pub(crate) trait HoconParseError<'a>:
+    ParseError<Input<'a>> + FromExternalError<Input<'a>, SyntaxErrorKind>
+{
+    /// Describes the error so it can be reported after the parser recovered from it.
+    fn into_document_error(self) -> DocumentError;
+}
+
+impl HoconParseError<'_> for DocumentError {
+    fn into_document_error(self) -> DocumentError {
+        self
+    }
+}
+
+impl<'a> HoconParseError<'a> for nom::error::Error<Input<'a>> {
+    fn into_document_error(self) -> DocumentError {
+        DocumentError::from_error_kind(self.input, self.code)
+    }
+}
+
+/// Creates an error which stops the parser from trying any alternatives.
+fn failure<'a, E: HoconParseError<'a>>(input: Input<'a>, kind: SyntaxErrorKind) -> nom::Err<E> {
+    nom::Err::Failure(E::from_external_error(input, ErrorKind::Fail, kind))
+}
+
+/// Runs a parser which must succeed, reporting the given kind of error when it fails at the start of the input.
+///
+/// Errors from further into the input are kept as they are closer to the actual mistake.
+fn expect<'a, O, E: HoconParseError<'a>>(
+    mut parser: impl Parser<Input<'a>, Output = O, Error = E>,
+    kind: SyntaxErrorKind,
+) -> impl Parser<Input<'a>, Output = O, Error = E> {
+    move |input: Input<'a>| match parser.parse(input.clone()) {
+        Err(nom::Err::Error(e)) => Err(nom::Err::Failure(
+            E::from_external_error(input, ErrorKind::Fail, kind).or(e),
+        )),
+        result => result,
+    }
+}
+
+/// The error used while parsing documents, keeping the furthest location reached by any of the alternatives as that
+/// is the most likely location of the actual mistake.
+#[derive(Debug)]
+pub(crate) struct DocumentError {
+    offset: usize,
+    kind: Option<SyntaxErrorKind>,
+    expected: Vec<String>,
+}
+
+impl DocumentError {
+    fn into_syntax_error(self, source: &str) -> SyntaxError {
+        SyntaxError::new(source, self.offset, self.kind, self.expected)
+    }
+}
+
+impl ParseError<Input<'_>> for DocumentError {
+    fn from_error_kind(input: Input<'_>, _: ErrorKind) -> Self {
+        DocumentError {
+            offset: input.location_offset(),
+            kind: None,
+            expected: vec![],
+        }
+    }
+
+    fn append(_: Input<'_>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: Input<'_>, c: char) -> Self {
+        DocumentError {
+            offset: input.location_offset(),
+            kind: None,
+            expected: vec![describe_char(c)],
+        }
+    }
+
+    fn or(mut self, other: Self) -> Self {
+        match self.offset.cmp(&other.offset) {
+            Ordering::Less => other,
+            Ordering::Greater => self,
+            // An error describing what went wrong is preferred over the tokens which were tried at that location.
+            Ordering::Equal if self.kind.is_some() => self,
+            Ordering::Equal if other.kind.is_some() => other,
+            Ordering::Equal => {
+                for token in other.expected {
+                    if !self.expected.contains(&token) {
+                        self.expected.push(token);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+impl FromExternalError<Input<'_>, SyntaxErrorKind> for DocumentError {
+    fn from_external_error(input: Input<'_>, _: ErrorKind, kind: SyntaxErrorKind) -> Self {
+        let expected = match kind {
+            SyntaxErrorKind::MissingSeparator => vec![describe_char(','), describe_char('\n')],
+            SyntaxErrorKind::MissingKeySeparator => ["':'", "'='", "'+='", "'{'"].map(String::from).to_vec(),
+            _ => vec![],
+        };
+        DocumentError {
+            offset: input.location_offset(),
+            kind: Some(kind),
+            expected,
+        }
+    }
+}
+
+/// The syntax a document is written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfigSyntax {
+    /// HOCON, of which the root must be an object, with or without braces.
+    #[default]
+    Conf,
+    /// JSON, of which the root is either an object or an array.
+    Json,
+    /// Java properties, see [`crate::properties::parse_properties`].
+    Properties,
+}
+
+impl ConfigSyntax {
+    /// The syntax of a file with the given extension, such as `conf`.
+    pub fn from_extension(extension: &str) -> Option<ConfigSyntax> {
+        match extension {
+            "conf" => Some(ConfigSyntax::Conf),
+            "json" => Some(ConfigSyntax::Json),
+            "properties" => Some(ConfigSyntax::Properties),
+            _ => None,
+        }
+    }
+
+    /// The extension of files in this syntax.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigSyntax::Conf => "conf",
+            ConfigSyntax::Json => "json",
+            ConfigSyntax::Properties => "properties",
+        }
+    }
 }
 
 /// Parses the given input as a Hocon document into a Hocon AST.
-pub fn parse<'a, E: ParseError<&'a str>>(input: &'a str) -> Result<HoconValue<'a>, HoconError> {
-    let r = alt((empty_content, parse_object)).parse(input);
+pub fn parse(input: &str) -> Result<HoconValue<'_>, HoconError> {
+    parse_with_syntax(input, ConfigSyntax::Conf)
+}
+
+/// Parses the given input as a document in the given syntax into a Hocon AST.
+///
+/// Only JSON documents may have an array at their root, as HOCON documents must be objects to be merged with other
+/// documents. An array root in a HOCON document results in [`HoconError::RootArray`].
+///
+/// Java properties are parsed by [`crate::properties::parse_properties`] instead, they result in
+/// [`HoconError::UnsupportedSyntax`].
+pub fn parse_with_syntax(input: &str, syntax: ConfigSyntax) -> Result<HoconValue<'_>, HoconError> {
+    let root = |input| match syntax {
+        ConfigSyntax::Conf => alt((array, parse_object)).parse(input),
+        _ => alt((array, braced_object)).parse(input),
+    };
+    let mut document = map(
+        all_consuming(delimited(whitespace, consumed(root), whitespace)),
+        |(root, value)| (Some(root), value),
+    );
+    let r: IResult<_, _, DocumentError> = match syntax {
+        ConfigSyntax::Conf => alt((map(empty_content, |value| (None, value)), document)).parse(Input::from(input)),
+        ConfigSyntax::Json => document.parse(Input::from(input)),
+        ConfigSyntax::Properties => return Err(HoconError::UnsupportedSyntax { syntax }),
+    };
     match r {
-        Ok((_, value)) => Ok(value),
-        Err(nom::Err::Error(e)) => {
-            let msg = convert_error(input, e);
-            Err(HoconError::ParseError { msg })
+        Ok((_, (Some(root), HoconValue::HoconArray(_)))) if syntax == ConfigSyntax::Conf => {
+            Err(HoconError::RootArray { span: Span::of(root) })
         }
-        _ => Err(HoconError::ParseError {
-            msg: "Unknown error".to_string(),
-        }),
+        Ok((_, (_, value))) => Ok(value),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(e.into_syntax_error(input).into()),
+        Err(nom::Err::Incomplete(_)) => Err(SyntaxError::new(input, input.len(), None, vec![]).into()),
+    }
+}
+
+/// Parses a path expression such as `a.b."c.d"`, in the same way as the path of a substitution.
+pub fn parse_path(input: &str) -> Result<HoconPath<'_>, HoconError> {
+    let r: IResult<_, _, DocumentError> = all_consuming(path_expression).parse(Input::from(input));
+    match r {
+        Ok((_, path)) => Ok(path),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(e.into_syntax_error(input).into()),
+        Err(nom::Err::Incomplete(_)) => Err(SyntaxError::new(input, input.len(), None, vec![]).into()),
     }
 }
 
-fn empty_content<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
+/// How severe a problem reported by [`parse_recovering`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a document, located by the span of the offending input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+/// Parses the given input as a Hocon document, continuing after errors to find as much of the AST as possible.
+///
+/// When a field or array element can't be parsed, the error is recorded and parsing resumes after the next newline or
+/// comma, or at the closing brace or bracket. The AST contains every element which could be parsed, along with a
+/// diagnostic for each error ordered by location. A document without errors yields the same AST as [`parse`].
+pub fn parse_recovering(input: &str) -> (HoconValue<'_>, Vec<Diagnostic>) {
+    let recovered = Recovered::default();
+    let root = alt((array, parse_object));
+    let r: IResult<_, _, DocumentError> =
+        delimited(whitespace, consumed(root), whitespace).parse(Input::new_extra(input, Some(recovered.clone())));
+
+    let mut diagnostics = vec![];
+    let value = match r {
+        Ok((remainder, (root, value))) => {
+            if !remainder.fragment().is_empty() {
+                recovered
+                    .borrow_mut()
+                    .push(DocumentError::from_error_kind(remainder, ErrorKind::Eof));
+            }
+            if let HoconValue::HoconArray(_) = value {
+                let span = Span::of(root);
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    span,
+                    message: HoconError::RootArray { span }.to_string(),
+                });
+            }
+            value
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            recovered.borrow_mut().push(e);
+            HoconValue::HoconObject(vec![])
+        }
+        Err(nom::Err::Incomplete(_)) => HoconValue::HoconObject(vec![]),
+    };
+
+    diagnostics.extend(recovered.take().into_iter().map(|error| {
+        let error = error.into_syntax_error(input);
+        Diagnostic {
+            severity: Severity::Error,
+            span: error.span,
+            message: error.to_string(),
+        }
+    }));
+    // Each unclosed value reports the same end of input while unwinding, of which one diagnostic is enough.
+    let mut seen = HashSet::new();
+    diagnostics.retain(|diagnostic| seen.insert((diagnostic.span, diagnostic.message.clone())));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    (value, diagnostics)
+}
+
+/// Splits a value such as `10s` or `512MiB` into its leading number and the unit text following it.
+///
+/// In a document such values are a value concatenation of a number and an unquoted string, which resolves to a
+/// string. This recovers the number so durations and sizes can be interpreted. Whitespace around the unit is ignored.
+pub fn number_prefix(value: &str) -> Option<(HoconValue<'_>, &str)> {
+    let (unit, number) = number::<nom::error::Error<Input<'_>>>(Input::from(value.trim())).ok()?;
+    Some((number, unit.fragment().trim_start()))
+}
+
+fn empty_content<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
     map(all_consuming(whitespace), |_| HoconValue::HoconObject(vec![])).parse(input)
 }
 
-fn null<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
+fn null<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
     let (input, _) = tag("null")(input)?;
     Ok((input, HoconValue::HoconNull))
 }
 
-fn boolean<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
+fn boolean<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
     let parse_true = value(HoconValue::HoconBoolean(true), tag("true"));
     let parse_false = value(HoconValue::HoconBoolean(false), tag("false"));
     alt((parse_true, parse_false)).parse(input)
 }
 
-fn is_hocon_whitespace(c: char) -> bool {
-    c.is_whitespace()
-        || c == '\t'
-        || c == '\n'
-        || c == '\u{000B}'
-        || c == '\u{000C}'
-        || c == '\r'
-        || c == '\u{001C}'
-        || c == '\u{001D}'
-        || c == '\u{001E}'
-        || c == '\u{001F}'
-}
+pub(crate) fn is_hocon_whitespace(c: char) -> bool {
+    c.is_whitespace()
+        || c == '\t'
+        || c == '\n'
+        || c == '\u{000B}'
+        || c == '\u{000C}'
+        || c == '\r'
+        || c == '\u{001C}'
+        || c == '\u{001D}'
+        || c == '\u{001E}'
+        || c == '\u{001F}'
+}
+
+/// Parses a `//` or `#` comment up to (but excluding) the end of the line.
+pub(crate) fn comment<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, &'a str, E> {
+    map(
+        preceded(alt((tag("//"), tag("#"))), take_till(|c| c == '\n')),
+        |comment: Input<'a>| *comment.fragment(),
+    )
+    .parse(input)
+}
+
+/// Skips any whitespace, including newlines and comments.
+fn whitespace<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, (), E> {
+    let (input, _) =
+        many0_count(alt((value((), take_while1(is_hocon_whitespace)), value((), comment)))).parse(input)?;
+    Ok((input, ()))
+}
+
+pub(crate) fn is_inline_whitespace(c: char) -> bool {
+    c != '\n' && is_hocon_whitespace(c)
+}
+
+/// Parses whitespace which doesn't cross into the next line, without skipping comments.
+fn inline_whitespace<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, &'a str, E> {
+    map(take_while(is_inline_whitespace), |whitespace: Input<'a>| {
+        *whitespace.fragment()
+    })
+    .parse(input)
+}
+
+fn is_forbidden_unquoted_char(c: char) -> bool {
+    is_hocon_whitespace(c)
+        || c == '$'
+        || c == '"'
+        || c == '{'
+        || c == '}'
+        || c == '['
+        || c == ']'
+        || c == ':'
+        || c == '='
+        || c == ','
+        || c == '+'
+        || c == '#'
+        || c == '`'
+        || c == '^'
+        || c == '?'
+        || c == '!'
+        || c == '@'
+        || c == '*'
+        || c == '&'
+        || c == '\\'
+}
+
+pub(crate) fn unquoted_string<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, &'a str, E> {
+    map(
+        recognize(many1((
+            not(peek(tag("//"))),
+            not(peek(verify(anychar, |&c| is_forbidden_unquoted_char(c)))),
+            anychar,
+        ))),
+        |value: Input<'a>| *value.fragment(),
+    )
+    .parse(input)
+}
+
+/// A piece of a path expression before it is split into the keys of the path.
+enum PathPiece<'a> {
+    Unquoted(&'a str),
+    Quoted(Cow<'a, str>),
+    Whitespace(&'a str),
+}
+
+/// Parses a path expression, a sequence of quoted and unquoted strings in which the unquoted parts are split on `.`.
+///
+/// Whitespace between the pieces is part of the key, an empty unquoted segment (as in `a..b`) is rejected.
+pub(crate) fn path_expression<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconPath<'a>, E> {
+    fn piece<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, (PathPiece<'a>, Span), E> {
+        map(
+            consumed(alt((
+                map(quoted_string, PathPiece::Quoted),
+                map(unquoted_string, PathPiece::Unquoted),
+            ))),
+            |(consumed, piece)| (piece, Span::of(consumed)),
+        )
+        .parse(input)
+    }
+
+    fn append<'a>(segment: &mut Option<(Cow<'a, str>, Span)>, value: Cow<'a, str>, span: Span) {
+        match segment {
+            Some((segment, segment_span)) => {
+                segment.to_mut().push_str(&value);
+                *segment_span = segment_span.to(span);
+            }
+            None => *segment = Some((value, span)),
+        }
+    }
+
+    fn into_path(pieces: Vec<(PathPiece<'_>, Span)>) -> Option<HoconPath<'_>> {
+        let mut segments = vec![];
+        let mut current = None;
+        for (piece, span) in pieces {
+            match piece {
+                PathPiece::Quoted(value) => append(&mut current, value, span),
+                PathPiece::Whitespace("") => {}
+                PathPiece::Whitespace(value) => append(&mut current, Cow::Borrowed(value), span),
+                PathPiece::Unquoted(value) => {
+                    let mut start = span.start;
+                    let mut parts = value.split('.').map(|part| {
+                        let span = Span {
+                            start,
+                            end: start + part.len(),
+                        };
+                        start = span.end + 1;
+                        (part, span)
+                    });
+                    if let Some((first, span)) = parts.next().filter(|(part, _)| !part.is_empty()) {
+                        append(&mut current, Cow::Borrowed(first), span);
+                    }
+                    for (part, span) in parts {
+                        segments.push(current.take()?);
+                        if !part.is_empty() {
+                            current = Some((Cow::Borrowed(part), span));
+                        }
+                    }
+                }
+            }
+        }
+        segments.push(current?);
+        let (segments, spans) = segments.into_iter().unzip();
+        Some(HoconPath { segments, spans })
+    }
+
+    let pieces = (piece, many0((recognize(inline_whitespace), piece)));
+    map_opt(pieces, |(first, rest)| {
+        let mut pieces = vec![first];
+        for (whitespace, piece) in rest {
+            pieces.push((PathPiece::Whitespace(whitespace.fragment()), Span::of(whitespace)));
+            pieces.push(piece);
+        }
+        into_path(pieces)
+    })
+    .parse(input)
+}
+
+/// A piece of a quoted string, either a run of literal characters or a single escape sequence.
+enum StringFragment<'a> {
+    Literal(&'a str),
+    Escaped(char),
+}
+
+/// Parses the 4 hex digits following a `\\u` escape.
+fn hex_code_unit<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, u16, E> {
+    map_opt(
+        take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+        |hex: Input<'a>| u16::from_str_radix(hex.fragment(), 16).ok(),
+    )
+    .parse(input)
+}
+
+/// Parses the remainder of a `\\u` escape, combining UTF-16 surrogate pairs into a single character.
+fn unicode_escape<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, char, E> {
+    fn surrogate_pair<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, char, E> {
+        map_opt(
+            (
+                verify(hex_code_unit, |high| (0xD800..0xDC00).contains(high)),
+                preceded(tag("\\u"), verify(hex_code_unit, |low| (0xDC00..0xE000).contains(low))),
+            ),
+            |(high, low)| char::decode_utf16([high, low]).next()?.ok(),
+        )
+        .parse(input)
+    }
+
+    alt((
+        surrogate_pair,
+        map_opt(hex_code_unit, |unit| char::from_u32(u32::from(unit))),
+    ))
+    .parse(input)
+}
+
+fn escaped_char<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, char, E> {
+    preceded(
+        char('\\'),
+        alt((
+            value('"', char('"')),
+            value('\\', char('\\')),
+            value('/', char('/')),
+            value('\u{0008}', char('b')),
+            value('\u{000C}', char('f')),
+            value('\n', char('n')),
+            value('\r', char('r')),
+            value('\t', char('t')),
+            preceded(char('u'), unicode_escape),
+        )),
+    )
+    .parse(input)
+}
+
+/// Parses a JSON compatible quoted string.
+///
+/// Strings without escape sequences are borrowed from the input, others are decoded into an owned string. Once the
+/// opening quote is found the string must be valid, an unterminated string is reported at the opening quote.
+pub(crate) fn quoted_string<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, Cow<'a, str>, E> {
+    fn literal<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, &'a str, E> {
+        map(
+            // JSON only forbids the unescaped control characters below U+0020, not the ones from U+007F.
+            take_till1(|c: char| c == '"' || c == '\\' || c < '\u{20}'),
+            |literal: Input<'a>| *literal.fragment(),
+        )
+        .parse(input)
+    }
+
+    let fragment = alt((
+        map(literal, StringFragment::Literal),
+        map(escaped_char, StringFragment::Escaped),
+    ));
+    let content = fold_many0(
+        fragment,
+        || Cow::Borrowed(""),
+        |mut acc: Cow<'a, str>, fragment| {
+            match fragment {
+                StringFragment::Literal(s) if acc.is_empty() => acc = Cow::Borrowed(s),
+                StringFragment::Literal(s) => acc.to_mut().push_str(s),
+                StringFragment::Escaped(c) => acc.to_mut().push(c),
+            }
+            acc
+        },
+    );
+
+    let (remainder, (_, value)) = (char('"'), content).parse(input.clone())?;
+    match remainder.fragment().chars().next() {
+        Some('"') => Ok((remainder.take_from(1), value)),
+        Some('\\') => Err(failure(remainder, SyntaxErrorKind::InvalidEscape)),
+        None | Some('\n' | '\r') => Err(failure(input, SyntaxErrorKind::UnterminatedString)),
+        Some(_) => Err(nom::Err::Failure(E::from_char(remainder, '"'))),
+    }
+}
+
+/// Parses a triple quoted string which may span multiple lines.
+///
+/// The string ends at the first `"""`, any additional quotes directly before the closing delimiter are part of the
+/// content. An unterminated string is reported at the opening delimiter.
+pub(crate) fn multiline_string<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, &'a str, E> {
+    let (content, _) = tag("\"\"\"")(input.clone())?;
+    match content.fragment().find("\"\"\"") {
+        Some(start) => {
+            let quotes = content.fragment()[start..].chars().take_while(|&c| c == '"').count();
+            let end = start + quotes - 3;
+            let (remainder, value) = content.take_split(end);
+            Ok((remainder.take_from(3), *value.fragment()))
+        }
+        None => Err(failure(input, SyntaxErrorKind::UnterminatedString)),
+    }
+}
+
+/// Parses a number following the JSON grammar, distinguishing integers from floating point numbers.
+pub(crate) fn number<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+    let fraction = (char('.'), digit1);
+    let exponent = (one_of("eE"), opt(one_of("+-")), digit1);
+    map_opt(
+        recognize((opt(char('-')), digit1, opt(fraction), opt(exponent))),
+        |literal: Input<'a>| {
+            let literal = *literal.fragment();
+            if literal.contains(['.', 'e', 'E']) {
+                literal
+                    .parse()
+                    .ok()
+                    .map(|value| HoconValue::Float(value, literal.into()))
+            } else {
+                Some(match literal.parse() {
+                    Ok(value) => HoconValue::Int(value, literal.into()),
+                    Err(_) => HoconValue::BigInt(literal.into()),
+                })
+            }
+        },
+    )
+    .parse(input)
+}
+
+pub(crate) fn include<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconInclusion<'a>, E> {
+    preceded(
+        (tag("include"), whitespace),
+        alt((
+            map(
+                preceded(tag("required"), delimited(char('('), include_resource, char(')'))),
+                |resource| HoconInclusion {
+                    resource,
+                    required: true,
+                },
+            ),
+            map(include_resource, HoconInclusion::from),
+        )),
+    )
+    .parse(input)
+}
+
+/// An include used as a value, which must name the kind of resource. A value such as `include "name"` is the
+/// concatenation of two strings instead.
+pub(crate) fn value_include<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconInclusion<'a>, E> {
+    verify(include, |inclusion| {
+        !matches!(inclusion.resource, IncludeResource::Heuristic(_))
+    })
+    .parse(input)
+}
+
+fn include_resource<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, IncludeResource<'a>, E> {
+    alt((
+        preceded(
+            tag("url"),
+            delimited(char('('), map(quoted_string, IncludeResource::Url), char(')')),
+        ),
+        preceded(
+            tag("file"),
+            delimited(char('('), map(quoted_string, IncludeResource::File), char(')')),
+        ),
+        preceded(
+            tag("classpath"),
+            delimited(char('('), map(quoted_string, IncludeResource::Classpath), char(')')),
+        ),
+        map(quoted_string, IncludeResource::Heuristic),
+    ))
+    .parse(input)
+}
+
+/// Parses a substitution, `${path}` or `${?path}` when the referenced value is allowed to be missing.
+fn substitution<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+    map(
+        consumed(delimited(
+            tag("${"),
+            (map(opt(char('?')), |optional| optional.is_some()), path_expression),
+            char('}'),
+        )),
+        |(consumed, (optional, path))| HoconValue::Substitution {
+            path,
+            optional,
+            span: Span::of(consumed),
+        },
+    )
+    .parse(input)
+}
+
+/// Runs the parser, wrapping its output along with the location of the input it consumed.
+fn spanned<'a, O, E: HoconParseError<'a>>(
+    parser: impl Parser<Input<'a>, Output = O, Error = E>,
+) -> impl Parser<Input<'a>, Output = Spanned<O>, Error = E> {
+    map(consumed(parser), |(consumed, value)| {
+        Spanned::new(value, Span::of(consumed))
+    })
+}
+
+/// Parses a single value which can take part in a value concatenation.
+fn concatenation_element<'a, E: HoconParseError<'a>>(
+    input: Input<'a>,
+) -> IResult<Input<'a>, Spanned<HoconValue<'a>>, E> {
+    spanned(alt((
+        null,
+        boolean,
+        number,
+        substitution,
+        array,
+        braced_object,
+        map(unquoted_string, |v| {
+            HoconValue::HoconString(HoconString::Unqouted(v.into()))
+        }),
+        map(multiline_string, |v| {
+            HoconValue::HoconString(HoconString::Multiline(v.into()))
+        }),
+        map(quoted_string, |v| HoconValue::HoconString(HoconString::Quoted(v))),
+    )))
+    .parse(input)
+}
+
+/// Parses one or more values on the same line. When there is more than one value, they form a value concatenation in
+/// which the whitespace between them is kept as unquoted strings.
+fn value_concatenation<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, Spanned<HoconValue<'a>>, E> {
+    map(
+        (
+            concatenation_element,
+            many0((recognize(inline_whitespace), concatenation_element)),
+        ),
+        |(first, rest)| {
+            let Some((_, last)) = rest.last() else {
+                return first;
+            };
+            let span = first.span.to(last.span);
+            let mut values = vec![first];
+            for (whitespace, value) in rest {
+                if !whitespace.fragment().is_empty() {
+                    values.push(Spanned::new(
+                        HoconValue::HoconString(HoconString::Unqouted((*whitespace.fragment()).into())),
+                        Span::of(whitespace),
+                    ));
+                }
+                values.push(value);
+            }
+            Spanned::new(HoconValue::Concatenation(values), span)
+        },
+    )
+    .parse(input)
+}
+
+fn parse_value<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, Spanned<HoconValue<'a>>, E> {
+    alt((
+        spanned(map(value_include, HoconValue::HoconInclude)),
+        value_concatenation,
+    ))
+    .parse(input)
+}
+
+/// Parses the elements of an object or array up to and including the closing character, or up to the end of the
+/// input for an object without braces.
+///
+/// Elements are separated by a comma or a newline, and a single trailing comma is allowed. A missing separator,
+/// a double comma or a missing closing character fails at the exact location of the problem. When recovering from
+/// errors, the error is recorded and parsing continues after the next newline or comma, or at the closing character.
+fn separated_elements<'a, O, E: HoconParseError<'a>>(
+    mut element: impl Parser<Input<'a>, Output = O, Error = E>,
+    closing: Option<char>,
+) -> impl Parser<Input<'a>, Output = Vec<O>, Error = E> {
+    move |input: Input<'a>| {
+        let mut elements = vec![];
+        let (mut input, _) = whitespace(input)?;
+        while !at_end(&input, closing) {
+            if let Some(closing) = closing.filter(|_| input.fragment().is_empty()) {
+                recover(&input, nom::Err::Failure(E::from_char(input.clone(), closing)))?;
+                return Ok((input, elements));
+            }
+            let parsed = element.parse(input.clone()).and_then(|(remainder, element)| {
+                elements.push(element);
+                element_separator(remainder, closing)
+            });
+            input = match parsed {
+                Ok((remainder, _)) => remainder,
+                Err(e) => {
+                    let offset = recover(&input, e)?;
+                    whitespace(resync(input, offset, closing))?.0
+                }
+            };
+        }
+        let input = match closing {
+            Some(closing) => input.take_from(closing.len_utf8()),
+            None => input,
+        };
+        Ok((input, elements))
+    }
+}
+
+fn at_end(input: &Input<'_>, closing: Option<char>) -> bool {
+    match closing {
+        Some(closing) => input.fragment().starts_with(closing),
+        None => input.fragment().is_empty(),
+    }
+}
+
+/// Parses the separator following an element, along with the whitespace around it.
+fn element_separator<'a, E: HoconParseError<'a>>(input: Input<'a>, closing: Option<char>) -> IResult<Input<'a>, (), E> {
+    let (remainder, skipped) = recognize(whitespace).parse(input)?;
+    let (remainder, comma) = opt(char(',')).parse(remainder)?;
+    let separated = comma.is_some() || skipped.fragment().contains('\n');
+    if !separated && !at_end(&remainder, closing) && !remainder.fragment().is_empty() {
+        return Err(failure(remainder, SyntaxErrorKind::MissingSeparator));
+    }
+
+    let (remainder, _) = whitespace(remainder)?;
+    if comma.is_some() && remainder.fragment().starts_with(',') {
+        return Err(nom::Err::Failure(E::from_error_kind(
+            remainder,
+            ErrorKind::SeparatedList,
+        )));
+    }
+    Ok((remainder, ()))
+}
+
+/// Records the error when parsing with [`parse_recovering`], yielding the offset at which it occurred. Otherwise the
+/// error is passed on.
+fn recover<'a, E: HoconParseError<'a>>(input: &Input<'a>, error: nom::Err<E>) -> Result<usize, nom::Err<E>> {
+    match (&input.extra, error) {
+        (Some(recovered), nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            let error = e.into_document_error();
+            let offset = error.offset;
+            recovered.borrow_mut().push(error);
+            Ok(offset)
+        }
+        (_, error) => Err(error),
+    }
+}
+
+/// Skips the input from the given offset up to and including the next newline or comma, or up to the closing
+/// character, whichever comes first.
+fn resync(input: Input<'_>, offset: usize, closing: Option<char>) -> Input<'_> {
+    let start = offset
+        .saturating_sub(input.location_offset())
+        .min(input.fragment().len());
+    let next = input.fragment()[start..]
+        .char_indices()
+        .find(|&(_, c)| c == '\n' || c == ',' || Some(c) == closing);
+    match next {
+        Some((i, c)) if Some(c) == closing => input.take_from(start + i),
+        Some((i, c)) => input.take_from(start + i + c.len_utf8()),
+        None => input.take_from(input.fragment().len()),
+    }
+}
+
+fn key_value<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconField<'a>, E> {
+    /// Parses the separator between key and value, yielding the location of the operator when it is `+=`.
+    fn separator<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, Option<Span>, E> {
+        alt((
+            map(tag("+="), |operator| Some(Span::of(operator))),
+            value(None, alt((char(':'), char('='), peek(char('{'))))),
+        ))
+        .parse(input)
+    }
+
+    let (input, (_, path, _, append, _, value)) = (
+        whitespace,
+        path_expression,
+        whitespace,
+        expect(separator, SyntaxErrorKind::MissingKeySeparator),
+        whitespace,
+        expect(parse_value, SyntaxErrorKind::MissingValue),
+    )
+        .parse(input)?;
+    let field = match append {
+        Some(span) => HoconField::Append(path, value, span),
+        None => HoconField::KeyValue(path, value),
+    };
+    Ok((input, field))
+}
+
+fn object_field<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconField<'a>, E> {
+    alt((
+        map(preceded(whitespace, spanned(include)), HoconField::Include),
+        key_value,
+    ))
+    .parse(input)
+}
+
+fn array<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+    preceded(
+        char('['),
+        map(
+            separated_elements(expect(parse_value, SyntaxErrorKind::MissingValue), Some(']')),
+            HoconValue::HoconArray,
+        ),
+    )
+    .parse(input)
+}
+
+fn braced_object<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+    fn parse_inner0<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+        map(
+            separated_elements(expect(object_field, SyntaxErrorKind::MissingKey), Some('}')),
+            HoconValue::HoconObject,
+        )
+        .parse(input)
+    }
+
+    preceded(char('{'), parse_inner0).parse(input)
+}
+
+fn parse_object<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+    fn parse_inner1<'a, E: HoconParseError<'a>>(input: Input<'a>) -> IResult<Input<'a>, HoconValue<'a>, E> {
+        map(
+            separated_elements(expect(object_field, SyntaxErrorKind::MissingKey), None),
+            HoconValue::HoconObject,
+        )
+        .parse(input)
+    }
+
+    alt((braced_object, parse_inner1)).parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use nom_language::error::VerboseError;
+
+    use super::*;
+    use crate::parser::HoconValue;
+
+    impl<'a> HoconParseError<'a> for VerboseError<Input<'a>> {
+        fn into_document_error(self) -> DocumentError {
+            let (input, _) = self.errors.into_iter().next().expect("Errors have at least one entry");
+            DocumentError::from_error_kind(input, ErrorKind::Fail)
+        }
+    }
+
+    /// Strips the location information from the error so it can be compared against plain strings.
+    fn into_str_error(e: VerboseError<Input<'_>>) -> VerboseError<&str> {
+        VerboseError {
+            errors: e
+                .errors
+                .into_iter()
+                .map(|(input, kind)| (*input.fragment(), kind))
+                .collect(),
+        }
+    }
+
+    /// Runs the parser on the input, exposing the remainder and errors as plain strings for easier comparisons.
+    fn run<'a, O>(
+        mut parser: impl Parser<Input<'a>, Output = O, Error = VerboseError<Input<'a>>>,
+        input: &'a str,
+    ) -> IResult<&'a str, O, VerboseError<&'a str>> {
+        match parser.parse(Input::from(input)) {
+            Ok((remainder, output)) => Ok((*remainder.fragment(), output)),
+            Err(e) => Err(e.map(into_str_error)),
+        }
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(run(null, "null"), Ok(("", HoconValue::HoconNull)));
+    }
+
+    #[test]
+    fn test_boolean_true() {
+        assert_eq!(run(boolean, "true"), Ok(("", HoconValue::HoconBoolean(true))));
+    }
+
+    #[test]
+    fn test_boolean_false() {
+        assert_eq!(run(boolean, "false"), Ok(("", HoconValue::HoconBoolean(false))));
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(run(whitespace, "     test"), Ok(("test", ())));
+    }
+
+    #[test]
+    fn test_unquoted_string() {
+        assert_eq!(run(unquoted_string, "test"), Ok(("", "test")));
+    }
+
+    #[test]
+    fn test_unquoted_string_with_trailing_slash_comment() {
+        assert_eq!(run(unquoted_string, "test// hello"), Ok(("// hello", "test")));
+    }
+
+    #[test]
+    fn test_unquoted_string_with_trailing_pound_comment() {
+        assert_eq!(run(unquoted_string, "test# hello"), Ok(("# hello", "test")));
+    }
+
+    #[test]
+    fn test_whitespace_with_comments() {
+        assert_eq!(run(whitespace, "  # hello\n  // world\n  test"), Ok(("test", ())));
+    }
+
+    #[test]
+    fn test_comment_stops_at_newline() {
+        assert_eq!(run(comment, "// hello\nworld"), Ok(("\nworld", " hello")));
+    }
+
+    #[test]
+    fn test_quoted_string() {
+        assert_eq!(run(quoted_string, "\"test\""), Ok(("", "test".into())));
+    }
+
+    #[test]
+    fn test_quoted_string_with_escaped_quote() {
+        assert_eq!(
+            run(quoted_string, "\"testy \\\" test\""),
+            Ok(("", "testy \" test".into()))
+        );
+    }
+
+    #[test]
+    fn test_quoted_string_with_any_characters() {
+        assert_eq!(
+            run(quoted_string, r#""hello world, a-b: {c} [d] $e 🦀""#),
+            Ok(("", "hello world, a-b: {c} [d] $e 🦀".into()))
+        );
+    }
+
+    #[test]
+    fn test_quoted_string_without_escapes_is_borrowed() {
+        let (_, value) = run(quoted_string, r#""a-b""#).unwrap();
+        assert!(matches!(value, Cow::Borrowed("a-b")));
+    }
+
+    #[test]
+    fn test_quoted_string_with_escapes_is_owned() {
+        let (_, value) = run(quoted_string, r#""C:\\path""#).unwrap();
+        assert!(matches!(value, Cow::Owned(_)));
+        assert_eq!(value, "C:\\path");
+    }
+
+    #[test]
+    fn test_quoted_string_escape_sequences() {
+        assert_eq!(
+            run(quoted_string, r#""\"\\\/\b\f\n\r\t""#),
+            Ok(("", "\"\\/\u{0008}\u{000C}\n\r\t".into()))
+        );
+    }
+
+    #[test]
+    fn test_quoted_string_unicode_escape() {
+        assert_eq!(run(quoted_string, r#""caf\u00e9 \u20AC""#), Ok(("", "café €".into())));
+    }
+
+    #[test]
+    fn test_quoted_string_surrogate_pair() {
+        assert_eq!(run(quoted_string, r#""\ud83e\udd80""#), Ok(("", "🦀".into())));
+    }
+
+    #[test]
+    fn test_quoted_string_lone_surrogate() {
+        assert!(run(quoted_string, r#""\ud83e""#).is_err());
+        assert!(run(quoted_string, r#""\udd80""#).is_err());
+    }
+
+    #[test]
+    fn test_quoted_string_invalid_escape() {
+        assert!(run(quoted_string, r#""\x""#).is_err());
+        assert!(run(quoted_string, r#""\u12""#).is_err());
+    }
+
+    #[test]
+    fn test_quoted_string_rejects_raw_newline() {
+        assert!(run(quoted_string, "\"hello\nworld\"").is_err());
+    }
+
+    #[test]
+    fn test_quoted_string_accepts_raw_delete() {
+        assert_eq!(
+            run(quoted_string, "\"a\u{7f}b\u{85}\""),
+            Ok(("", "a\u{7f}b\u{85}".into()))
+        );
+        assert!(run(quoted_string, "\"a\u{1f}\"").is_err());
+    }
+
+    #[test]
+    fn test_multiline_string() {
+        assert_eq!(
+            run(multiline_string, "\"\"\"SELECT *\n  FROM \"table\"\"\"\" rest"),
+            Ok((" rest", "SELECT *\n  FROM \"table\""))
+        );
+    }
+
+    #[test]
+    fn test_multiline_string_no_escapes() {
+        assert_eq!(run(multiline_string, r#""""C:\path\n""""#), Ok(("", r"C:\path\n")));
+    }
+
+    #[test]
+    fn test_multiline_string_extra_trailing_quotes() {
+        assert_eq!(run(multiline_string, r#""""foo"""""#), Ok(("", r#"foo""#)));
+        assert_eq!(run(multiline_string, r#""""foo""""""#), Ok(("", r#"foo"""#)));
+    }
+
+    #[test]
+    fn test_multiline_string_empty() {
+        assert_eq!(run(multiline_string, r#""""""""#), Ok(("", "")));
+    }
+
+    #[test]
+    fn test_multiline_string_unterminated() {
+        let input = r#""""never closed ""#;
+        match run(multiline_string, input) {
+            Err(nom::Err::Failure(e)) => assert_eq!(e.errors[0].0, input),
+            other => panic!("Expected failure at opening delimiter, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_path_expression_single_key() {
+        assert_eq!(
+            run(path_expression, "hello = 1"),
+            Ok((" = 1", HoconPath::from(["hello"])))
+        );
+    }
+
+    #[test]
+    fn test_path_expression_dotted() {
+        assert_eq!(
+            run(path_expression, "akka.actor.provider"),
+            Ok(("", HoconPath::from(["akka", "actor", "provider"])))
+        );
+    }
+
+    #[test]
+    fn test_path_expression_quoted_segment() {
+        assert_eq!(
+            run(path_expression, r#"a."b.c".d"#),
+            Ok(("", HoconPath::from(["a", "b.c", "d"])))
+        );
+    }
+
+    #[test]
+    fn test_path_expression_joined_pieces() {
+        assert_eq!(
+            run(path_expression, r#"a"b"c.d e:"#),
+            Ok((":", HoconPath::from(["abc", "d e"])))
+        );
+    }
+
+    #[test]
+    fn test_path_expression_empty_quoted_segment() {
+        assert_eq!(
+            run(path_expression, r#"a."".b"#),
+            Ok(("", HoconPath::from(["a", "", "b"])))
+        );
+    }
+
+    #[test]
+    fn test_path_expression_empty_segment() {
+        assert!(run(path_expression, "a..b").is_err());
+        assert!(run(path_expression, ".a").is_err());
+        assert!(run(path_expression, "a.").is_err());
+    }
+
+    #[test]
+    fn test_path_display() {
+        assert_eq!(HoconPath::from(["a", "b"]).to_string(), "a.b");
+        assert_eq!(
+            HoconPath::from(["a", "b.c", "", "d e"]).to_string(),
+            r#"a."b.c".""."d e""#
+        );
+    }
+
+    #[test]
+    fn test_substitution() {
+        assert_eq!(
+            run(substitution, "${a.b}"),
+            Ok((
+                "",
+                HoconValue::Substitution {
+                    path: HoconPath::from(["a", "b"]),
+                    optional: false,
+                    span: Span { start: 0, end: 6 },
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_optional_substitution() {
+        assert_eq!(
+            run(substitution, r#"${?"a.b".c} rest"#),
+            Ok((
+                " rest",
+                HoconValue::Substitution {
+                    path: HoconPath::from(["a.b", "c"]),
+                    optional: true,
+                    span: Span { start: 0, end: 11 },
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_substitution_requires_path() {
+        assert!(run(substitution, "${}").is_err());
+        assert!(run(substitution, "${?}").is_err());
+        assert!(run(substitution, "${a").is_err());
+    }
+
+    #[test]
+    fn test_value_concatenation() {
+        assert_eq!(
+            run(value_concatenation, "foo  bar\nnext"),
+            Ok((
+                "\nnext",
+                HoconValue::Concatenation(vec![
+                    HoconValue::HoconString(HoconString::Unqouted("foo".into())).into(),
+                    HoconValue::HoconString(HoconString::Unqouted("  ".into())).into(),
+                    HoconValue::HoconString(HoconString::Unqouted("bar".into())).into(),
+                ])
+                .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_number_followed_by_unit() {
+        assert_eq!(
+            run(value_concatenation, "512MiB"),
+            Ok((
+                "",
+                HoconValue::Concatenation(vec![
+                    HoconValue::Int(512, "512".into()).into(),
+                    HoconValue::HoconString(HoconString::Unqouted("MiB".into())).into(),
+                ])
+                .into()
+            ))
+        );
+        assert_eq!(
+            run(value_concatenation, "1.2.3"),
+            Ok((
+                "",
+                HoconValue::Concatenation(vec![
+                    HoconValue::Float(1.2, "1.2".into()).into(),
+                    HoconValue::HoconString(HoconString::Unqouted(".3".into())).into(),
+                ])
+                .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_number_prefix() {
+        assert_eq!(number_prefix("10s"), Some((HoconValue::Int(10, "10".into()), "s")));
+        assert_eq!(
+            number_prefix(" 1.5 GiB "),
+            Some((HoconValue::Float(1.5, "1.5".into()), "GiB"))
+        );
+        assert_eq!(number_prefix("42"), Some((HoconValue::Int(42, "42".into()), "")));
+        assert_eq!(number_prefix("ms"), None);
+    }
+
+    #[test]
+    fn test_single_value_is_not_a_concatenation() {
+        assert_eq!(
+            run(value_concatenation, "foo "),
+            Ok((" ", HoconValue::HoconString(HoconString::Unqouted("foo".into())).into()))
+        );
+    }
+
+    #[test]
+    fn test_key_value() {
+        assert_eq!(
+            run(key_value, "test = true"),
+            Ok((
+                "",
+                HoconField::KeyValue(HoconPath::from(["test"]), HoconValue::HoconBoolean(true).into())
+            ))
+        );
+    }
+
+    #[test]
+    fn test_key_value_append() {
+        assert_eq!(
+            run(key_value, "list += 1"),
+            Ok((
+                "",
+                HoconField::Append(
+                    HoconPath::from(["list"]),
+                    HoconValue::Int(1, "1".into()).into(),
+                    Span { start: 5, end: 7 }
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(run(number, "42"), Ok(("", HoconValue::Int(42, "42".into()))));
+    }
+
+    #[test]
+    fn test_number_integer_and_float() {
+        assert_eq!(run(number, "-7"), Ok(("", HoconValue::Int(-7, "-7".into()))));
+        assert_eq!(run(number, "042"), Ok(("", HoconValue::Int(42, "042".into()))));
+        assert_eq!(run(number, "42.0"), Ok(("", HoconValue::Float(42.0, "42.0".into()))));
+        assert_eq!(
+            run(number, "-1.5e3"),
+            Ok(("", HoconValue::Float(-1500.0, "-1.5e3".into())))
+        );
+        assert_eq!(run(number, "2E-2"), Ok(("", HoconValue::Float(0.02, "2E-2".into()))));
+    }
+
+    #[test]
+    fn test_number_keeps_precision() {
+        assert_eq!(
+            run(number, "9007199254740993"),
+            Ok(("", HoconValue::Int(9007199254740993, "9007199254740993".into())))
+        );
+        assert_eq!(
+            run(number, "18446744073709551616"),
+            Ok(("", HoconValue::BigInt("18446744073709551616".into())))
+        );
+    }
+
+    #[test]
+    fn test_number_requires_digits() {
+        assert!(run(number, "info").is_err());
+        assert!(run(number, "-x").is_err());
+        assert_eq!(run(number, "1."), Ok((".", HoconValue::Int(1, "1".into()))));
+    }
+
+    #[test]
+    fn test_array() {
+        let expected_data = vec![
+            HoconValue::Int(1, "1".into()).into(),
+            HoconValue::Int(2, "2".into()).into(),
+            HoconValue::Int(3, "3".into()).into(),
+        ];
+        assert_eq!(run(array, "[1,2,3]"), Ok(("", HoconValue::HoconArray(expected_data))));
+    }
+
+    #[test]
+    fn test_array_trailing_comma() {
+        assert_eq!(
+            run(array, "[1,2,3,]"),
+            Ok((
+                "",
+                HoconValue::HoconArray(vec![
+                    HoconValue::Int(1, "1".into()).into(),
+                    HoconValue::Int(2, "2".into()).into(),
+                    HoconValue::Int(3, "3".into()).into(),
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_array_new_lines_equate_commas() {
+        assert_eq!(run(array, "[1\n2\n3]"), run(array, "[1,2,3]"));
+    }
+
+    #[test]
+    fn parse_basic_json_object() {
+        let content = r#"{ "hello": "world" }"#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["hello"]),
+            HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_json_object_with_two_keys() {
+        let content = r#"{ "hello": "world", "world": "hello" }"#;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["hello"]),
+                HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
+            ),
+            HoconField::KeyValue(
+                HoconPath::from(["world"]),
+                HoconValue::HoconString(HoconString::Quoted("hello".into())).into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_json_object_with_two_keys_multiline() {
+        let content = r#"{
+            "hello": "world",
+            "world": "hello"
+        }"#;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["hello"]),
+                HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
+            ),
+            HoconField::KeyValue(
+                HoconPath::from(["world"]),
+                HoconValue::HoconString(HoconString::Quoted("hello".into())).into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_hocon_object_with_two_keys() {
+        let content = r#"{
+            hello: "world"
+            world: "hello"
+        }"#;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["hello"]),
+                HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
+            ),
+            HoconField::KeyValue(
+                HoconPath::from(["world"]),
+                HoconValue::HoconString(HoconString::Quoted("hello".into())).into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_escaped_key() {
+        let content = r#"{ "a\tb": "\u0041" }"#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["a\tb"]),
+            HoconValue::HoconString(HoconString::Quoted("A".into())).into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_multiline_string_value() {
+        let content = r#"
+            pem = """-----BEGIN KEY-----
+            "quoted" // not a comment
+            -----END KEY-----"""
+            after = 1
+        "#;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["pem"]),
+                HoconValue::HoconString(HoconString::Multiline(
+                    "-----BEGIN KEY-----\n            \"quoted\" // not a comment\n            -----END KEY-----"
+                        .into(),
+                ))
+                .into(),
+            ),
+            HoconField::KeyValue(HoconPath::from(["after"]), HoconValue::Int(1, "1".into()).into()),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_unterminated_multiline_string() {
+        let content = "a = 1\nb = \"\"\"open\n";
+        match parse(content) {
+            Err(HoconError::ParseError(e)) => {
+                assert_eq!(e.kind, SyntaxErrorKind::UnterminatedString);
+                assert_eq!(e.position, Position { line: 1, column: 4 });
+            }
+            other => panic!("Expected parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_dotted_key() {
+        let content = r#"akka.actor."provider.class" = cluster"#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["akka", "actor", "provider.class"]),
+            HoconValue::HoconString(HoconString::Unqouted("cluster".into())).into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_substitution_field_value() {
+        let content = "a = 1\nb = ${a}";
+        let expected = vec![
+            HoconField::KeyValue(HoconPath::from(["a"]), HoconValue::Int(1, "1".into()).into()),
+            HoconField::KeyValue(
+                HoconPath::from(["b"]),
+                HoconValue::Substitution {
+                    path: HoconPath::from(["a"]),
+                    optional: false,
+                    span: Span { start: 10, end: 14 },
+                }
+                .into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_substitution_array_elements() {
+        let content = "list = [${a}, ${?b}]";
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["list"]),
+            HoconValue::HoconArray(vec![
+                HoconValue::Substitution {
+                    path: HoconPath::from(["a"]),
+                    optional: false,
+                    span: Span { start: 8, end: 12 },
+                }
+                .into(),
+                HoconValue::Substitution {
+                    path: HoconPath::from(["b"]),
+                    optional: true,
+                    span: Span { start: 14, end: 19 },
+                }
+                .into(),
+            ])
+            .into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_substitution_in_concatenation() {
+        let content = r#"path = ${base}"/bin" ${?extra}"#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["path"]),
+            HoconValue::Concatenation(vec![
+                HoconValue::Substitution {
+                    path: HoconPath::from(["base"]),
+                    optional: false,
+                    span: Span { start: 7, end: 14 },
+                }
+                .into(),
+                HoconValue::HoconString(HoconString::Quoted("/bin".into())).into(),
+                HoconValue::HoconString(HoconString::Unqouted(" ".into())).into(),
+                HoconValue::Substitution {
+                    path: HoconPath::from(["extra"]),
+                    optional: true,
+                    span: Span { start: 21, end: 30 },
+                }
+                .into(),
+            ])
+            .into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_full_line_comments() {
+        let content = r#"
+            # pound comment
+            hello = "world"
+            // slash comment
+            world = "hello"
+        "#;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["hello"]),
+                HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
+            ),
+            HoconField::KeyValue(
+                HoconPath::from(["world"]),
+                HoconValue::HoconString(HoconString::Quoted("hello".into())).into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
+
+    #[test]
+    fn parse_trailing_comments() {
+        let content = r#"
+            hello = world # pound comment
+            answer = 42 // slash comment
+            enabled = true// no space
+        "#;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["hello"]),
+                HoconValue::HoconString(HoconString::Unqouted("world".into())).into(),
+            ),
+            HoconField::KeyValue(HoconPath::from(["answer"]), HoconValue::Int(42, "42".into()).into()),
+            HoconField::KeyValue(HoconPath::from(["enabled"]), HoconValue::HoconBoolean(true).into()),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
 
-fn whitespace<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
-    let (input, _) = take_while(is_hocon_whitespace)(input)?;
-    Ok((input, ()))
-}
+    #[test]
+    fn parse_comments_in_array() {
+        let content = r#"
+            values = [ # opening comment
+                1, // first
+                # on its own line
+                2
+                3 # last
+            ]
+        "#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["values"]),
+            HoconValue::HoconArray(vec![
+                HoconValue::Int(1, "1".into()).into(),
+                HoconValue::Int(2, "2".into()).into(),
+                HoconValue::Int(3, "3".into()).into(),
+            ])
+            .into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
 
-fn unquoted_string<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
-    recognize(many1((
-        not(peek(tag("//"))),
-        not(peek(verify(anychar, |&c| {
-            is_hocon_whitespace(c)
-                || c == '$'
-                || c == '"'
-                || c == '{'
-                || c == '}'
-                || c == '['
-                || c == ']'
-                || c == ':'
-                || c == '='
-                || c == ','
-                || c == '+'
-                || c == '#'
-                || c == '`'
-                || c == '^'
-                || c == '?'
-                || c == '!'
-                || c == '@'
-                || c == '*'
-                || c == '&'
-                || c == '\\'
-        }))),
-        anychar,
-    )))
-    .parse(input)
-}
+    #[test]
+    fn parse_comments_in_nested_object() {
+        let content = r#"
+            // leading comment
+            outer { # after brace
+                // inside
+                inner = 1 # trailing
+                # before closing brace
+            } // after brace
+        "#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["outer"]),
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["inner"]),
+                HoconValue::Int(1, "1".into()).into(),
+            )])
+            .into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
 
-fn quoted_string<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
-    fn is_string_char(c: char) -> bool {
-        !(c.is_alphanum() || c == '.')
+    #[test]
+    fn parse_comments_before_root_braces() {
+        let content = r#"# header
+            { "hello": "world" }
+        "#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["hello"]),
+            HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
     }
 
-    fn parse_str<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
-        input.split_at_position_complete(is_string_char)
+    #[test]
+    fn parse_comments_in_quoted_string() {
+        let content = r##"
+            url = "http://example.com" // real comment
+            tag = "#hashtag"
+        "##;
+        let expected = vec![
+            HoconField::KeyValue(
+                HoconPath::from(["url"]),
+                HoconValue::HoconString(HoconString::Quoted("http://example.com".into())).into(),
+            ),
+            HoconField::KeyValue(
+                HoconPath::from(["tag"]),
+                HoconValue::HoconString(HoconString::Quoted("#hashtag".into())).into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
     }
 
-    delimited(tag("\""), parse_str, tag("\"")).parse(input)
-}
+    #[test]
+    fn parse_only_comments() {
+        let content = r#"
+            # nothing to see here
+            // move along
+        "#;
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(vec![])));
+    }
 
-fn number<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-    map(double, HoconValue::HoconNumber).parse(input)
-}
+    #[test]
+    fn parse_inclusion() {
+        let content = r#"include file("test.conf")"#;
+        let expected = HoconInclusion::from(IncludeResource::File("test.conf".into()));
+        assert_eq!(run(include, content), Ok(("", expected)));
+    }
 
-fn include<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconInclusion<'a>, E> {
-    let (remainder, (_, _, (_, v))) = (
-        tag("include"),
-        whitespace,
-        alt((
+    #[test]
+    fn parse_inclusion_forms() {
+        let inclusion = |resource, required| HoconInclusion { resource, required };
+        let cases = [
             (
-                tag("url"),
-                delimited(char('('), map(quoted_string, HoconInclusion::Url), char(')')),
+                r#"include "a.conf""#,
+                inclusion(IncludeResource::Heuristic("a.conf".into()), false),
             ),
             (
-                tag("file"),
-                delimited(char('('), map(quoted_string, HoconInclusion::File), char(')')),
+                r#"include url("http://a")"#,
+                inclusion(IncludeResource::Url("http://a".into()), false),
             ),
             (
-                tag("classpath"),
-                delimited(char('('), map(quoted_string, HoconInclusion::Classpath), char(')')),
+                r#"include classpath("a")"#,
+                inclusion(IncludeResource::Classpath("a".into()), false),
             ),
-        )),
-    )
-        .parse(input)?;
-    Ok((remainder, v))
-}
-
-fn parse_value<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-    alt((
-        null,
-        map(include, HoconValue::HoconInclude),
-        boolean,
-        number,
-        array,
-        parse_object,
-        map(unquoted_string, |v| HoconValue::HoconString(HoconString::Unqouted(v))),
-        map(quoted_string, |v| HoconValue::HoconString(HoconString::Quoted(v))),
-    ))
-    .parse(input)
-}
-
-fn next_element_whitespace<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
-    map((whitespace, opt(char(','))), |_| ()).parse(input)
-}
-
-fn key_value<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (&'a str, HoconValue<'a>), E> {
-    fn separator<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
-        map(alt((char(':'), char('='), peek(char('{')))), |_| ()).parse(input)
+            (
+                r#"include required("a")"#,
+                inclusion(IncludeResource::Heuristic("a".into()), true),
+            ),
+            (
+                r#"include required(file("a"))"#,
+                inclusion(IncludeResource::File("a".into()), true),
+            ),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(run(include, content), Ok(("", expected)), "{content}");
+        }
+        assert!(run(include, r#"include required"#).is_err());
+        assert!(run(include, r#"include unknown("a")"#).is_err());
     }
 
-    let (input, (_, path, _, _, _, value, _)) = (
-        whitespace,
-        alt((quoted_string, unquoted_string)),
-        whitespace,
-        separator,
-        whitespace,
-        parse_value,
-        next_element_whitespace,
-    )
-        .parse(input)?;
-    Ok((input, (path, value)))
-}
-
-fn object_field<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconField<'a>, E> {
-    alt((
-        map(include, HoconField::Include),
-        map(key_value, |(k, v)| HoconField::KeyValue(k, v)),
-    ))
-    .parse(input)
-}
-
-fn array<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-    fn array_element<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-        preceded(whitespace, parse_value).parse(input)
+    #[test]
+    fn parse_inclusion_like_value() {
+        let content = r#"a = include "b""#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["a"]),
+            HoconValue::Concatenation(vec![
+                HoconValue::HoconString(HoconString::Unqouted("include".into())).into(),
+                HoconValue::HoconString(HoconString::Unqouted(" ".into())).into(),
+                HoconValue::HoconString(HoconString::Quoted("b".into())).into(),
+            ])
+            .into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
     }
 
-    delimited(
-        char('['),
-        map(
-            terminated(
-                separated_list0(alt((char(','), char('\n'))), array_element),
-                opt(char(',')),
+    #[test]
+    fn parse_inclusion_merge() {
+        let content = r#"include file("test.conf")
+            hello = "world"
+        "#;
+        let expected = vec![
+            HoconField::Include(HoconInclusion::from(IncludeResource::File("test.conf".into())).into()),
+            HoconField::KeyValue(
+                HoconPath::from(["hello"]),
+                HoconValue::HoconString(HoconString::Quoted("world".into())).into(),
             ),
-            HoconValue::HoconArray,
-        ),
-        char(']'),
-    )
-    .parse(input)
-}
-
-fn parse_object<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-    fn parse_inner0<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-        map(many0(object_field), HoconValue::HoconObject).parse(input)
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
     }
 
-    fn parse_inner1<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, HoconValue<'a>, E> {
-        map(many1(object_field), HoconValue::HoconObject).parse(input)
+    #[test]
+    fn pares_inclusion_value() {
+        let content = r#"
+            hello = include file("test.conf")
+        "#;
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["hello"]),
+            HoconValue::HoconInclude(HoconInclusion::from(IncludeResource::File("test.conf".into()))).into(),
+        )];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
     }
 
-    alt((delimited(char('{'), parse_inner0, char('}')), parse_inner1)).parse(input)
-}
+    #[test]
+    fn parse_empty_line() {
+        assert_eq!(run(empty_content, ""), Ok(("", HoconValue::HoconObject(vec![]))));
+        assert_eq!(parse(""), Ok(HoconValue::HoconObject(vec![])));
+    }
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn parse_empty_line_whitespace() {
+        assert_eq!(parse("   "), Ok(HoconValue::HoconObject(vec![])));
+    }
 
-    use nom_language::error::VerboseError;
+    #[test]
+    fn parse_empty_multiline() {
+        let content = r#"
 
-    use super::*;
-    use crate::parser::HoconValue;
+        "#;
+        let expected = vec![];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
+    }
 
-    #[test]
-    fn test_null() {
-        assert_eq!(null::<VerboseError<&str>>("null"), Ok(("", HoconValue::HoconNull)));
+    /// Asserts the document fails to parse, with the error pointing at the given column of the last line.
+    fn parse_error(content: &str) -> SyntaxError {
+        match parse(content) {
+            Err(HoconError::ParseError(e)) => e,
+            other => panic!("Expected parse error for {content:?}, got {other:?}"),
+        }
     }
 
-    #[test]
-    fn test_boolean_true() {
-        assert_eq!(
-            boolean::<VerboseError<&str>>("true"),
-            Ok(("", HoconValue::HoconBoolean(true)))
-        );
+    fn assert_parse_error_at(content: &str, column: usize) {
+        let error = parse_error(content);
+        let line = content.lines().count() - 1;
+        assert_eq!(error.position, Position { line, column }, "{error}");
+        assert_eq!(error.snippet, content.lines().last().unwrap());
     }
 
     #[test]
-    fn test_boolean_false() {
-        assert_eq!(
-            boolean::<VerboseError<&str>>("false"),
-            Ok(("", HoconValue::HoconBoolean(false)))
-        );
+    fn parse_fields_separated_by_newlines_and_commas() {
+        let content = r#"
+            a = 1, b = 2
+            c = 3 // comment
+            , d = 4
+            e = [
+                5 # comment
+                6,
+                7, // trailing comma
+            ]
+        "#;
+        let expected = vec![
+            HoconField::KeyValue(HoconPath::from(["a"]), HoconValue::Int(1, "1".into()).into()),
+            HoconField::KeyValue(HoconPath::from(["b"]), HoconValue::Int(2, "2".into()).into()),
+            HoconField::KeyValue(HoconPath::from(["c"]), HoconValue::Int(3, "3".into()).into()),
+            HoconField::KeyValue(HoconPath::from(["d"]), HoconValue::Int(4, "4".into()).into()),
+            HoconField::KeyValue(
+                HoconPath::from(["e"]),
+                HoconValue::HoconArray(vec![
+                    HoconValue::Int(5, "5".into()).into(),
+                    HoconValue::Int(6, "6".into()).into(),
+                    HoconValue::Int(7, "7".into()).into(),
+                ])
+                .into(),
+            ),
+        ];
+        assert_eq!(parse(content), Ok(HoconValue::HoconObject(expected)));
     }
 
     #[test]
-    fn test_whitespace() {
-        assert_eq!(whitespace::<VerboseError<&str>>("     test"), Ok(("test", ())));
+    fn parse_trailing_comma_in_object() {
+        let expected = vec![HoconField::KeyValue(
+            HoconPath::from(["a"]),
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["b"]),
+                HoconValue::Int(1, "1".into()).into(),
+            )])
+            .into(),
+        )];
+        assert_eq!(parse("a { b = 1, }, "), Ok(HoconValue::HoconObject(expected)));
     }
 
     #[test]
-    fn test_unquoted_string() {
-        assert_eq!(unquoted_string::<VerboseError<&str>>("test"), Ok(("", "test")));
+    fn parse_missing_separator() {
+        assert_parse_error_at(r#"{ "a" : 1 "b" : 2 }"#, 14);
+        assert_parse_error_at("a = 1\nb = [1] c = 2", 10);
     }
 
     #[test]
-    fn test_unquoted_string_with_trailing_slash_comment() {
-        assert_eq!(
-            unquoted_string::<VerboseError<&str>>("test// hello"),
-            Ok(("// hello", "test"))
-        );
+    fn parse_double_comma() {
+        assert_parse_error_at("a = 1,, b = 2", 6);
+        assert_parse_error_at("a = [1,\n  , 2]", 2);
+        assert_parse_error_at("a { b = 1,, }", 10);
+        assert_parse_error_at("a = 1,,", 6);
     }
 
     #[test]
-    fn test_unquoted_string_with_trailing_pound_comment() {
-        assert_eq!(
-            unquoted_string::<VerboseError<&str>>("test# hello"),
-            Ok(("# hello", "test"))
-        );
+    fn parse_leading_comma() {
+        assert!(parse("a = [, 1]").is_err());
+        assert!(parse("{ , a = 1 }").is_err());
     }
 
     #[test]
-    fn test_quoted_string() {
-        assert_eq!(quoted_string::<VerboseError<&str>>("\"test\""), Ok(("", "test")));
+    fn parse_trailing_content_after_root_object() {
+        assert_parse_error_at("{ a = 1 } b", 10);
     }
 
-    #[ignore]
     #[test]
-    fn test_quoted_string_with_escaped_quote() {
+    fn parse_json_root_array() {
+        let content = r#"
+            // shared fixture
+            [
+                { "name": "a" },
+                { "name": "b" }
+            ]
+        "#;
+        let element = |name| {
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["name"]),
+                HoconValue::HoconString(HoconString::Quoted(Cow::Borrowed(name))).into(),
+            )])
+        };
         assert_eq!(
-            quoted_string::<VerboseError<&str>>("\"testy \\\" test\""),
-            Ok(("", "testy \" test"))
+            parse_with_syntax(content, ConfigSyntax::Json),
+            Ok(HoconValue::HoconArray(vec![element("a").into(), element("b").into()]))
         );
     }
 
     #[test]
-    fn test_key_value() {
+    fn parse_json_root_object() {
         assert_eq!(
-            key_value::<VerboseError<&str>>("test = true"),
-            Ok(("", ("test", HoconValue::HoconBoolean(true))))
+            parse_with_syntax(r#"{ "a": 1 }"#, ConfigSyntax::Json),
+            Ok(HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["a"]),
+                HoconValue::Int(1, "1".into()).into(),
+            )]))
         );
     }
 
     #[test]
-    fn test_number() {
+    fn parse_properties_syntax_is_unsupported() {
         assert_eq!(
-            number::<VerboseError<&str>>("42"),
-            Ok(("", HoconValue::HoconNumber(42f64)))
+            parse_with_syntax("a.b = 1", ConfigSyntax::Properties),
+            Err(HoconError::UnsupportedSyntax {
+                syntax: ConfigSyntax::Properties
+            })
         );
     }
 
     #[test]
-    fn test_array() {
-        let expected_data = vec![
-            HoconValue::HoconNumber(1f64),
-            HoconValue::HoconNumber(2f64),
-            HoconValue::HoconNumber(3f64),
-        ];
-        assert_eq!(
-            array::<VerboseError<&str>>("[1,2,3]"),
-            Ok(("", HoconValue::HoconArray(expected_data)))
-        );
+    fn parse_path_expression() {
+        assert_eq!(parse_path("a.\"b.c\".d"), Ok(HoconPath::from(["a", "b.c", "d"])));
+        assert!(parse_path("a..b").is_err());
+        assert!(parse_path("").is_err());
     }
 
     #[test]
-    fn test_array_trailing_comma() {
-        assert_eq!(
-            array::<VerboseError<&str>>("[1,2,3,]"),
-            Ok((
-                "",
-                HoconValue::HoconArray(vec![
-                    HoconValue::HoconNumber(1.0),
-                    HoconValue::HoconNumber(2.0),
-                    HoconValue::HoconNumber(3.0),
-                ])
-            ))
-        );
+    fn test_config_syntax_extension() {
+        for syntax in [ConfigSyntax::Conf, ConfigSyntax::Json, ConfigSyntax::Properties] {
+            assert_eq!(ConfigSyntax::from_extension(syntax.extension()), Some(syntax));
+        }
+        assert_eq!(ConfigSyntax::from_extension("txt"), None);
     }
 
     #[test]
-    fn test_array_new_lines_equate_commas() {
-        assert_eq!(
-            array::<VerboseError<&str>>("[1\n2\n3]"),
-            array::<VerboseError<&str>>("[1,2,3]")
-        );
+    fn parse_json_requires_root_value() {
+        assert!(parse_with_syntax("", ConfigSyntax::Json).is_err());
+        assert!(parse_with_syntax(r#""a": 1"#, ConfigSyntax::Json).is_err());
     }
 
     #[test]
-    fn parse_basic_json_object() {
-        let content = r#"{ "hello": "world" }"#;
-        let expected = vec![HoconField::KeyValue(
-            "hello",
-            HoconValue::HoconString(HoconString::Quoted("world")),
-        )];
+    fn parse_root_array_in_hocon() {
+        let error = parse("  [1, 2] // list").unwrap_err();
         assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
+            error,
+            HoconError::RootArray {
+                span: Span { start: 2, end: 8 }
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "The root of a HOCON document must be an object, arrays are only allowed at the root of JSON documents"
         );
     }
 
     #[test]
-    fn parse_json_object_with_two_keys() {
-        let content = r#"{ "hello": "world", "world": "hello" }"#;
-        let expected = vec![
-            HoconField::KeyValue("hello", HoconValue::HoconString(HoconString::Quoted("world"))),
-            HoconField::KeyValue("world", HoconValue::HoconString(HoconString::Quoted("hello"))),
-        ];
+    fn parse_spans() {
+        let content = "a.b = [1, x]\ninclude file(\"f.conf\")\nc = ${a.\"b\"} y";
+        let span_of = |text: &str| {
+            let start = content.find(text).unwrap();
+            Span {
+                start,
+                end: start + text.len(),
+            }
+        };
+        let Ok(HoconValue::HoconObject(fields)) = parse(content) else {
+            panic!("Expected object");
+        };
+
+        let HoconField::KeyValue(path, value) = &fields[0] else {
+            panic!("Expected key value");
+        };
+        assert_eq!(path.spans, vec![Span { start: 0, end: 1 }, Span { start: 2, end: 3 }]);
+        assert_eq!(path.span(), Some(span_of("a.b")));
+        assert_eq!(value.span, span_of("[1, x]"));
+        let HoconValue::HoconArray(elements) = &value.value else {
+            panic!("Expected array");
+        };
+        let element_spans: Vec<_> = elements.iter().map(|element| element.span).collect();
+        assert_eq!(element_spans, vec![span_of("1"), span_of("x")]);
+
+        let HoconField::Include(include) = &fields[1] else {
+            panic!("Expected include");
+        };
+        assert_eq!(include.span, span_of("include file(\"f.conf\")"));
+
+        let HoconField::KeyValue(path, value) = &fields[2] else {
+            panic!("Expected key value");
+        };
+        assert_eq!(path.spans, vec![Span { start: 36, end: 37 }]);
+        assert_eq!(value.span, span_of("${a.\"b\"} y"));
+        let HoconValue::Concatenation(elements) = &value.value else {
+            panic!("Expected concatenation");
+        };
+        let HoconValue::Substitution { path, span, .. } = &elements[0].value else {
+            panic!("Expected substitution");
+        };
+        assert_eq!(*span, span_of("${a.\"b\"}"));
+        assert_eq!(elements[0].span, *span);
+        assert_eq!(path.spans, vec![Span { start: 42, end: 43 }, span_of("\"b\"")]);
+        let element_spans: Vec<_> = elements[1..].iter().map(|element| element.span).collect();
         assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
+            element_spans,
+            vec![Span { start: 48, end: 49 }, Span { start: 49, end: 50 }]
         );
     }
 
     #[test]
-    fn parse_json_object_with_two_keys_multiline() {
-        let content = r#"{
-            "hello": "world",
-            "world": "hello"
-        }"#;
-        let expected = vec![
-            HoconField::KeyValue("hello", HoconValue::HoconString(HoconString::Quoted("world"))),
-            HoconField::KeyValue("world", HoconValue::HoconString(HoconString::Quoted("hello"))),
+    fn parse_error_kinds() {
+        let cases = [
+            ("a = \"abc", SyntaxErrorKind::UnterminatedString, 4),
+            ("a = \"a\\qb\"", SyntaxErrorKind::InvalidEscape, 6),
+            ("a = \"a\tb\"", SyntaxErrorKind::UnexpectedChar('\t'), 6),
+            ("a = 1 b = 2", SyntaxErrorKind::MissingSeparator, 8),
+            ("a = }", SyntaxErrorKind::MissingValue, 4),
+            ("a = [1, }]", SyntaxErrorKind::MissingValue, 8),
+            ("{ = 1 }", SyntaxErrorKind::MissingKey, 2),
+            ("a b", SyntaxErrorKind::MissingKeySeparator, 3),
+            ("a { b = 1", SyntaxErrorKind::UnexpectedEnd, 9),
+            ("a = 1,, b = 2", SyntaxErrorKind::UnexpectedChar(','), 6),
         ];
-        assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
-        );
+        for (content, kind, column) in cases {
+            let error = parse_error(content);
+            assert_eq!((error.kind, error.position.column), (kind, column), "{content:?}");
+        }
     }
 
     #[test]
-    fn parse_hocon_object_with_two_keys() {
-        let content = r#"{
-            hello: "world"
-            world: "hello"
-        }"#;
-        let expected = vec![
-            HoconField::KeyValue("hello", HoconValue::HoconString(HoconString::Quoted("world"))),
-            HoconField::KeyValue("world", HoconValue::HoconString(HoconString::Quoted("hello"))),
-        ];
+    fn parse_error_expected_tokens() {
+        assert_eq!(parse_error("a = [1, 2").expected, vec!["']'"]);
+        assert_eq!(parse_error("a = 1 b = 2").expected, vec!["','", "newline"]);
+        assert_eq!(parse_error("a = ${b").expected, vec!["'}'"]);
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let error = parse_error("a = 1\nb = \"x\\qy\"\nc = 3");
+        assert_eq!(error.span, Span { start: 12, end: 13 });
+        assert_eq!(error.position, Position { line: 1, column: 6 });
+        assert_eq!(error.snippet, "b = \"x\\qy\"");
+    }
+
+    #[test]
+    fn parse_error_message() {
+        let error = parse("a = 1\nb = [1] c = 2").unwrap_err();
         assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
+            error.to_string(),
+            "Parse error at line 2, column 11: Missing separator, expected ',' or newline"
         );
+        let source = std::error::Error::source(&error).expect("Parse errors have a source");
+        assert_eq!(source.to_string(), "Missing separator, expected ',' or newline");
+    }
+
+    fn error(start: usize, end: usize, message: &str) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            span: Span { start, end },
+            message: message.to_string(),
+        }
     }
 
     #[test]
-    fn parse_inclusion() {
-        let content = r#"include file("test.conf")"#;
-        let expected = HoconInclusion::File("test.conf");
-        assert_eq!(include::<VerboseError<&str>>(content), Ok(("", expected)));
+    fn parse_recovering_valid_document() {
+        let content = "a { b = [1, 2] }\nc = ${a.b}";
+        assert_eq!(parse_recovering(content), (parse(content).unwrap(), vec![]));
     }
 
     #[test]
-    fn parse_inclusion_merge() {
-        let content = r#"include file("test.conf")
-            hello = "world"
-        "#;
-        let expected = vec![
-            HoconField::Include(HoconInclusion::File("test.conf")),
-            HoconField::KeyValue("hello", HoconValue::HoconString(HoconString::Quoted("world"))),
+    fn parse_recovering_skips_to_next_line() {
+        let content = "a = \"abc\nb = 2\nc = ${d\ne = 3";
+        let expected = HoconValue::HoconObject(vec![
+            HoconField::KeyValue(HoconPath::from(["b"]), HoconValue::Int(2, "2".into()).into()),
+            HoconField::KeyValue(HoconPath::from(["e"]), HoconValue::Int(3, "3".into()).into()),
+        ]);
+        let diagnostics = vec![
+            error(4, 5, "Unterminated string"),
+            error(22, 23, "Unexpected character '\\n', expected '}'"),
         ];
-        assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
-        );
+        assert_eq!(parse_recovering(content), (expected, diagnostics));
     }
 
     #[test]
-    fn pares_inclusion_value() {
-        let content = r#"
-            hello = include file("test.conf")
-        "#;
-        let expected = vec![HoconField::KeyValue(
-            "hello",
-            HoconValue::HoconInclude(HoconInclusion::File("test.conf")),
-        )];
-        assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
-        );
+    fn parse_recovering_within_nested_values() {
+        let content = "a { b = \"x\\q\", c = [1, }, 3] }\nd = 4";
+        let expected = HoconValue::HoconObject(vec![
+            HoconField::KeyValue(
+                HoconPath::from(["a"]),
+                HoconValue::HoconObject(vec![HoconField::KeyValue(
+                    HoconPath::from(["c"]),
+                    HoconValue::HoconArray(vec![
+                        HoconValue::Int(1, "1".into()).into(),
+                        HoconValue::Int(3, "3".into()).into(),
+                    ])
+                    .into(),
+                )])
+                .into(),
+            ),
+            HoconField::KeyValue(HoconPath::from(["d"]), HoconValue::Int(4, "4".into()).into()),
+        ]);
+        let diagnostics = vec![error(10, 11, "Invalid escape sequence"), error(23, 24, "Missing value")];
+        assert_eq!(parse_recovering(content), (expected, diagnostics));
     }
 
     #[test]
-    fn parse_empty_line() {
+    fn parse_recovering_separators() {
+        let content = "a = 1,, b = 2\nc = [1 {}] }\nd = 3";
+        let (value, diagnostics) = parse_recovering(content);
+        let HoconValue::HoconObject(fields) = value else {
+            panic!("Expected object");
+        };
+        let keys: Vec<_> = fields
+            .iter()
+            .filter_map(|field| match field {
+                HoconField::KeyValue(path, _) => Some(path.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(keys, vec!["a", "b", "c", "d"]);
         assert_eq!(
-            empty_content::<VerboseError<&str>>(""),
-            Ok(("", HoconValue::HoconObject(vec![])))
+            diagnostics,
+            vec![
+                error(6, 7, "Unexpected character ','"),
+                error(25, 26, "Missing separator, expected ',' or newline"),
+            ]
         );
-        assert_eq!(parse::<VerboseError<&str>>(""), Ok(HoconValue::HoconObject(vec![])));
     }
 
     #[test]
-    fn parse_empty_line_whitespace() {
-        assert_eq!(parse::<VerboseError<&str>>("   "), Ok(HoconValue::HoconObject(vec![])));
+    fn parse_recovering_unclosed_object() {
+        let content = "a { b = 1";
+        let expected = HoconValue::HoconObject(vec![HoconField::KeyValue(
+            HoconPath::from(["a"]),
+            HoconValue::HoconObject(vec![HoconField::KeyValue(
+                HoconPath::from(["b"]),
+                HoconValue::Int(1, "1".into()).into(),
+            )])
+            .into(),
+        )]);
+        let diagnostics = vec![error(9, 9, "Unexpected end of input, expected '}'")];
+        assert_eq!(parse_recovering(content), (expected, diagnostics));
     }
 
     #[test]
-    fn parse_empty_multiline() {
-        let content = r#"
+    fn parse_recovering_reports_unclosed_values_once() {
+        let (_, diagnostics) = parse_recovering("[[[");
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+        // The first one reports the array at the root.
+        assert_eq!(diagnostics[0].span, Span { start: 0, end: 3 });
+        assert_eq!(diagnostics[1], error(3, 3, "Unexpected end of input, expected ']'"));
+    }
 
-        "#;
-        let expected = vec![];
+    #[test]
+    fn parse_recovering_root_array() {
+        let (value, diagnostics) = parse_recovering("[1] x");
         assert_eq!(
-            parse::<VerboseError<&str>>(content),
-            Ok(HoconValue::HoconObject(expected))
+            value,
+            HoconValue::HoconArray(vec![HoconValue::Int(1, "1".into()).into()])
+        );
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].span, Span { start: 0, end: 3 });
+        assert_eq!(diagnostics[1], error(4, 5, "Unexpected character 'x'"));
+    }
+
+    #[test]
+    fn parse_into_owned() {
+        let content = String::from(
+            "a { b = [1, 2.5, 99999999999999999999], c = \"\"\"x\"\"\" }\nd = ${a.b} \"e\"\ninclude file(\"f\")",
         );
+        let copied = parse(&content).unwrap().into_owned();
+        let owned: HoconValue<'static> = content.parse().unwrap();
+        drop(content);
+        assert_eq!(copied, owned);
+        let HoconValue::HoconObject(fields) = &owned else {
+            panic!("Expected an object, got {owned:?}");
+        };
+        let HoconField::KeyValue(path, value) = &fields[1] else {
+            panic!("Expected a field, got {:?}", fields[1]);
+        };
+        assert_eq!(path.spans, vec![Span { start: 54, end: 55 }]);
+        assert_eq!(value.span, Span { start: 58, end: 68 });
     }
 }
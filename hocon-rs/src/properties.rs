@@ -0,0 +1,425 @@
+use std::borrow::Cow;
+
+use indexmap::IndexMap;
+
+use crate::{
+    parser::{HoconError, HoconField, HoconPath, HoconString, HoconValue, SyntaxError, SyntaxErrorKind},
+    span::{Span, Spanned},
+};
+
+/// Parses a Java properties file into a Hocon AST, following the rules of `java.util.Properties`.
+///
+/// Keys are separated from their value by `=`, `:` or whitespace. Lines starting with `#` or `!` are comments and a
+/// line ending in a backslash continues on the next line. The escapes `\t`, `\n`, `\r`, `\f` and `\uXXXX` are
+/// expanded, any other escaped character stands for itself. All values are strings.
+///
+/// Like Lightbend config, keys are split on dots into nested objects, so `a.b = 1` becomes `a { b = "1" }`. When a
+/// key is assigned both a string and an object, as in `a = 1` and `a.b = 2`, the object is kept regardless of the
+/// order of the lines. Otherwise the last assignment of a key wins.
+pub fn parse_properties(input: &str) -> Result<HoconValue<'_>, HoconError> {
+    let mut root = IndexMap::new();
+    for line in logical_lines(input) {
+        let (key, value) = line.entry(input)?;
+        let key_span = key[0].span.to(key[key.len() - 1].span);
+        insert(&mut root, &key, key_span, value);
+    }
+    Ok(into_value(root))
+}
+
+/// A key in a properties file along with the values or objects nested below it.
+enum Node<'a> {
+    Value(Cow<'a, str>),
+    Object(IndexMap<Cow<'a, str>, Field<'a>>),
+}
+
+/// A node along with the location of the segment of the key it was assigned to.
+struct Field<'a> {
+    key: Span,
+    node: Spanned<Node<'a>>,
+}
+
+fn insert<'a>(fields: &mut IndexMap<Cow<'a, str>, Field<'a>>, path: &[Text<'a>], key_span: Span, value: Text<'a>) {
+    let Some((key, rest)) = path.split_first() else {
+        return;
+    };
+    if rest.is_empty() {
+        let field = Field {
+            key: key.span,
+            node: value.map(Node::Value),
+        };
+        match fields.get_mut(&key.value) {
+            Some(Field {
+                node: Spanned {
+                    value: Node::Object(_), ..
+                },
+                ..
+            }) => {}
+            Some(existing) => *existing = field,
+            None => {
+                fields.insert(key.value.clone(), field);
+            }
+        }
+        return;
+    }
+
+    let object = || Field {
+        key: key.span,
+        node: Spanned::new(Node::Object(IndexMap::new()), key_span),
+    };
+    let entry = fields.entry(key.value.clone()).or_insert_with(object);
+    if let Node::Value(_) = entry.node.value {
+        *entry = object();
+    }
+    if let Node::Object(fields) = &mut entry.node.value {
+        insert(fields, rest, key_span, value);
+    }
+}
+
+fn into_value<'a>(fields: IndexMap<Cow<'a, str>, Field<'a>>) -> HoconValue<'a> {
+    let fields = fields.into_iter().map(|(key, field)| {
+        let value = field.node.map(|node| match node {
+            Node::Value(value) => HoconValue::HoconString(HoconString::Quoted(value)),
+            Node::Object(fields) => into_value(fields),
+        });
+        let path = HoconPath {
+            segments: vec![key],
+            spans: vec![field.key],
+        };
+        HoconField::KeyValue(path, value)
+    });
+    HoconValue::HoconObject(fields.collect())
+}
+
+/// Splits a segment of a key on the dots written as escapes such as `\u002e`, which are located at the whole segment.
+fn segments(segment: Text<'_>) -> Vec<Text<'_>> {
+    let span = segment.span;
+    match segment.value {
+        Cow::Borrowed(key) => key
+            .split('.')
+            .map(|key| Spanned::new(Cow::Borrowed(key), span))
+            .collect(),
+        Cow::Owned(key) => key
+            .split('.')
+            .map(|key| Spanned::new(Cow::Owned(key.to_owned()), span))
+            .collect(),
+    }
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\x0c')
+}
+
+/// A key or value along with its location in the file.
+type Text<'a> = Spanned<Cow<'a, str>>;
+
+/// A logical line, which is made up of one or more lines of the file joined by line continuations.
+struct Line<'a> {
+    text: Cow<'a, str>,
+    /// The offset within the text and within the file at which each of the joined lines starts.
+    starts: Vec<(usize, usize)>,
+}
+
+impl<'a> Line<'a> {
+    /// Splits the line into the segments of its key and its value, expanding the escapes in both. Keys are split on
+    /// their dots, keeping empty segments.
+    fn entry(&self, source: &str) -> Result<(Vec<Text<'a>>, Text<'a>), HoconError> {
+        let mut escaped = false;
+        let key_end = self
+            .text
+            .char_indices()
+            .find(|&(_, c)| match c {
+                _ if escaped => {
+                    escaped = false;
+                    false
+                }
+                '\\' => {
+                    escaped = true;
+                    false
+                }
+                c => c == '=' || c == ':' || is_whitespace(c),
+            })
+            .map_or(self.text.len(), |(i, _)| i);
+        let rest = self.text[key_end..].trim_start_matches(is_whitespace);
+        let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+        let value_start = self.text.len() - rest.trim_start_matches(is_whitespace).len();
+
+        let mut key = vec![];
+        let mut start = 0;
+        for end in self.text[..key_end].match_indices('.').map(|(i, _)| i).chain([key_end]) {
+            key.extend(segments(self.unescape(start, end, source)?));
+            start = end + 1;
+        }
+        let value = self.unescape(value_start, self.text.len(), source)?;
+        Ok((key, value))
+    }
+
+    fn unescape(&self, start: usize, end: usize, source: &str) -> Result<Text<'a>, HoconError> {
+        let raw = match &self.text {
+            Cow::Borrowed(text) => Cow::Borrowed(&text[start..end]),
+            Cow::Owned(text) => Cow::Owned(text[start..end].to_owned()),
+        };
+        let value = unescape(raw).map_err(|i| {
+            let kind = Some(SyntaxErrorKind::InvalidEscape);
+            SyntaxError::new(source, self.offset(start + i, false), kind, vec![])
+        })?;
+        let span = Span {
+            start: self.offset(start, false),
+            end: self.offset(end, true),
+        };
+        Ok(Spanned::new(value, span))
+    }
+
+    /// Converts an offset within the text into an offset within the file. An offset at which a continuation line
+    /// starts is placed at the end of the previous line when it is the end of a range.
+    fn offset(&self, index: usize, end: bool) -> usize {
+        let (line_start, file_start) = self
+            .starts
+            .iter()
+            .rev()
+            .find(|&&(line_start, _)| line_start < index || (!end && line_start == index))
+            .copied()
+            .unwrap_or(self.starts[0]);
+        file_start + index - line_start
+    }
+}
+
+/// Expands the escapes in the string, returning the offset of the escape which is malformed otherwise.
+fn unescape(raw: Cow<'_, str>) -> Result<Cow<'_, str>, usize> {
+    if !raw.contains('\\') {
+        return Ok(raw);
+    }
+    let hex_unit = |i: usize| {
+        let digits = raw
+            .get(i + 2..i + 6)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))?;
+        u16::from_str_radix(digits, 16).ok()
+    };
+
+    let mut unescaped = String::with_capacity(raw.len());
+    let mut chars = raw.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, 'u')) => {
+                let mut units = vec![hex_unit(i).ok_or(i)?];
+                chars.nth(3);
+                // Characters outside of the basic multilingual plane are written as a pair of UTF-16 surrogates.
+                if (0xD800..0xDC00).contains(&units[0]) && raw[i + 6..].starts_with("\\u") {
+                    if let Some(low) = hex_unit(i + 6).filter(|low| (0xDC00..0xE000).contains(low)) {
+                        units.push(low);
+                        chars.nth(5);
+                    }
+                }
+                unescaped.push(char::decode_utf16(units).next().and_then(Result::ok).ok_or(i)?);
+            }
+            Some((_, 't')) => unescaped.push('\t'),
+            Some((_, 'n')) => unescaped.push('\n'),
+            Some((_, 'r')) => unescaped.push('\r'),
+            Some((_, 'f')) => unescaped.push('\x0c'),
+            Some((_, c)) => unescaped.push(c),
+            None => {}
+        }
+    }
+    Ok(Cow::Owned(unescaped))
+}
+
+/// Splits the input into its logical lines, skipping blank lines and comments.
+fn logical_lines(input: &str) -> Vec<Line<'_>> {
+    let mut natural_lines = natural_lines(input);
+    let mut lines = vec![];
+    while let Some((offset, line)) = natural_lines.next() {
+        let content = line.trim_start_matches(is_whitespace);
+        if content.is_empty() || content.starts_with(['#', '!']) {
+            continue;
+        }
+        let mut logical = Line {
+            text: Cow::Borrowed(content),
+            starts: vec![(0, offset + line.len() - content.len())],
+        };
+        let mut current = content;
+        while continues(current) {
+            let text = logical.text.to_mut();
+            text.pop();
+            let Some((offset, line)) = natural_lines.next() else {
+                break;
+            };
+            let content = line.trim_start_matches(is_whitespace);
+            logical.starts.push((text.len(), offset + line.len() - content.len()));
+            text.push_str(content);
+            current = content;
+        }
+        lines.push(logical);
+    }
+    lines
+}
+
+/// Whether the line ends in an unescaped backslash.
+fn continues(line: &str) -> bool {
+    let backslashes = line.len() - line.trim_end_matches('\\').len();
+    backslashes % 2 == 1
+}
+
+/// Splits the input on `\n`, `\r` and `\r\n`, yielding each line along with its offset.
+fn natural_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset >= input.len() {
+            return None;
+        }
+        let rest = &input[offset..];
+        let end = rest.find(['\r', '\n']).unwrap_or(rest.len());
+        let terminator = if rest[end..].starts_with("\r\n") { 2 } else { 1 };
+        let line = (offset, &rest[..end]);
+        offset += end + terminator;
+        Some(line)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> Spanned<HoconValue<'_>> {
+        HoconValue::HoconString(HoconString::Quoted(value.into())).into()
+    }
+
+    fn field<'a>(key: &'a str, value: Spanned<HoconValue<'a>>) -> HoconField<'a> {
+        HoconField::KeyValue(HoconPath::from([key]), value)
+    }
+
+    fn object(fields: Vec<HoconField<'_>>) -> Spanned<HoconValue<'_>> {
+        HoconValue::HoconObject(fields).into()
+    }
+
+    #[test]
+    fn test_separators() {
+        let content = "a=1\nb:2\nc 3\nd = 4\ne\t:  5\nf\ng=";
+        let expected = HoconValue::HoconObject(vec![
+            field("a", string("1")),
+            field("b", string("2")),
+            field("c", string("3")),
+            field("d", string("4")),
+            field("e", string("5")),
+            field("f", string("")),
+            field("g", string("")),
+        ]);
+        assert_eq!(parse_properties(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let content = "# comment\n  ! another = comment\n\n   \na = b # not a comment\r\nc = d\r";
+        let expected = HoconValue::HoconObject(vec![field("a", string("b # not a comment")), field("c", string("d"))]);
+        assert_eq!(parse_properties(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_line_continuations() {
+        let content = "fruits = apple, \\\n         banana, \\\r\n    pear\nescaped = a\\\\\nnext = b\\";
+        let expected = HoconValue::HoconObject(vec![
+            field("fruits", string("apple, banana, pear")),
+            field("escaped", string("a\\")),
+            field("next", string("b")),
+        ]);
+        assert_eq!(parse_properties(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_escapes() {
+        let content = r"key\ with\:separators\=x = tab\tnew\nline \u0041\u00E9\uD83D\ude00 \q";
+        let expected = HoconValue::HoconObject(vec![field("key with:separators=x", string("tab\tnew\nline Aé😀 q"))]);
+        assert_eq!(parse_properties(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_invalid_unicode_escape() {
+        for (content, column) in [("a = \\u00g1", 4), ("a = b\\u12", 5), ("a = \\uD83D", 4)] {
+            match parse_properties(content) {
+                Err(HoconError::ParseError(e)) => {
+                    assert_eq!(e.kind, SyntaxErrorKind::InvalidEscape);
+                    assert_eq!(e.position.column, column, "{content}");
+                }
+                other => panic!("Expected invalid escape for {content}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_dotted_keys() {
+        let content = "a.b.c = 1\nx = 2\na.b.d = 3\na.e = 4\nx = 5";
+        let expected = HoconValue::HoconObject(vec![
+            field(
+                "a",
+                object(vec![
+                    field("b", object(vec![field("c", string("1")), field("d", string("3"))])),
+                    field("e", string("4")),
+                ]),
+            ),
+            field("x", string("5")),
+        ]);
+        assert_eq!(parse_properties(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_object_wins_over_string() {
+        let expected = HoconValue::HoconObject(vec![field("a", object(vec![field("b", string("2"))]))]);
+        assert_eq!(parse_properties("a = 1\na.b = 2"), Ok(expected.clone()));
+        assert_eq!(parse_properties("a.b = 2\na = 1"), Ok(expected));
+    }
+
+    #[test]
+    fn test_spans() {
+        let content = "a.b = x\\\n    yz\n";
+        let HoconValue::HoconObject(fields) = parse_properties(content).unwrap() else {
+            panic!("Expected an object");
+        };
+        let HoconField::KeyValue(key, a) = &fields[0] else {
+            panic!("Expected a field");
+        };
+        assert_eq!(a.span, Span { start: 0, end: 3 });
+        assert_eq!(key.spans, vec![Span { start: 0, end: 1 }]);
+        let HoconValue::HoconObject(fields) = &a.value else {
+            panic!("Expected an object");
+        };
+        let HoconField::KeyValue(key, b) = &fields[0] else {
+            panic!("Expected a field");
+        };
+        assert_eq!(b.span, Span { start: 6, end: 15 });
+        assert_eq!(key.spans, vec![Span { start: 2, end: 3 }]);
+    }
+
+    #[test]
+    fn test_key_spans() {
+        // The escaped dot splits the key like a plain one, both of its segments are located at the escape.
+        let HoconValue::HoconObject(fields) = parse_properties(r"a\u002eb.c = 1").unwrap() else {
+            panic!("Expected an object");
+        };
+        let mut spans = vec![];
+        let mut fields = &fields;
+        while let Some(HoconField::KeyValue(key, value)) = fields.first() {
+            spans.extend(key.spans.iter().copied());
+            match &value.value {
+                HoconValue::HoconObject(nested) => fields = nested,
+                _ => break,
+            }
+        }
+        assert_eq!(
+            spans,
+            vec![
+                Span { start: 0, end: 8 },
+                Span { start: 0, end: 8 },
+                Span { start: 9, end: 10 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixture() {
+        let content = include_str!("../../tests/java-properties.conf");
+        let expected = HoconValue::HoconObject(vec![field("hello", string("world"))]);
+        assert_eq!(parse_properties(content), Ok(expected));
+    }
+}
@@ -0,0 +1,897 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{
+    merge::{expand_paths, merge_field, merge_objects},
+    parser::{HoconError, HoconField, HoconPath, HoconString, HoconValue},
+    span::Spanned,
+};
+
+/// Resolves all substitutions in the document, replacing them with the values they refer to.
+///
+/// Substitutions are looked up from the root of the document. An optional substitution which can't be found removes
+/// the field it is assigned to, or the element of the array or value concatenation it is part of.
+///
+/// A substitution referring to the field whose value contains it, such as `path = ${path}":/extra"`, is
+/// self-referential and resolves to the value that field had before this assignment.
+///
+/// Only the value which is looked up has to be resolved: with `a { x = 1, y = ${b.x} }` and `b = ${a}`, `b.x` is
+/// found within `a` while `a` itself is still being resolved.
+///
+/// Value concatenations are evaluated once their substitutions are resolved: simple values are joined into a string
+/// keeping the whitespace between them, arrays are concatenated and objects are merged.
+///
+/// Fields with the same key are merged in the resolved document: when both values are objects they are merged
+/// recursively, otherwise the later value wins.
+pub fn resolve(document: HoconValue<'_>) -> Result<HoconValue<'_>, HoconError> {
+    let root = expand_paths(document);
+    let mut resolver = Resolver {
+        root: &root,
+        stack: vec![],
+        resolved: HashMap::new(),
+    };
+    let resolved = resolver.resolve_value(&root, &HoconPath::default())?;
+    Ok(resolved.unwrap_or(HoconValue::HoconObject(vec![])))
+}
+
+struct Resolver<'r, 'a> {
+    root: &'r HoconValue<'a>,
+    /// The fields currently being resolved, used to detect cycles and self-referential substitutions.
+    stack: Vec<(HoconPath<'a>, *const HoconValue<'a>)>,
+    /// The values of the paths looked up so far, such that a path referred to by many substitutions is only resolved
+    /// once. Self-referential lookups only see part of the fields and are never stored.
+    resolved: HashMap<Vec<Cow<'a, str>>, Option<HoconValue<'a>>>,
+}
+
+impl<'a> Resolver<'_, 'a> {
+    /// Resolves the value of the field at the given path, failing when that field is already being resolved.
+    fn resolve_at(
+        &mut self,
+        value: &HoconValue<'a>,
+        path: HoconPath<'a>,
+    ) -> Result<Option<HoconValue<'a>>, HoconError> {
+        if let Some(start) = self
+            .stack
+            .iter()
+            .position(|(_, resolving)| std::ptr::eq(*resolving, value))
+        {
+            let chain = self.stack[start..]
+                .iter()
+                .map(|(path, _)| path)
+                .chain([&path])
+                .map(ToString::to_string)
+                .collect();
+            return Err(HoconError::SubstitutionCycle { chain });
+        }
+
+        self.stack.push((path.clone(), value));
+        let resolved = self.resolve_value(value, &path);
+        self.stack.pop();
+        resolved
+    }
+
+    /// Resolves the substitutions within the value. Returns `None` when the value is an optional substitution which
+    /// can't be found.
+    fn resolve_value(
+        &mut self,
+        value: &HoconValue<'a>,
+        path: &HoconPath<'a>,
+    ) -> Result<Option<HoconValue<'a>>, HoconError> {
+        match value {
+            HoconValue::HoconObject(fields) => {
+                let mut resolved = vec![];
+                for field in fields {
+                    match field {
+                        HoconField::KeyValue(key, value) => {
+                            if let Some(resolved_value) = self.resolve_at(value, join(path, &key.segments))? {
+                                merge_field(&mut resolved, key.clone(), Spanned::new(resolved_value, value.span));
+                            }
+                        }
+                        include => resolved.push(include.clone()),
+                    }
+                }
+                Ok(Some(HoconValue::HoconObject(resolved)))
+            }
+            HoconValue::HoconArray(values) => Ok(Some(HoconValue::HoconArray(self.resolve_elements(values, path)?))),
+            HoconValue::Concatenation(values) => {
+                let mut resolved = self.resolve_elements(values, path)?;
+                if resolved.iter().all(|value| is_whitespace(value)) {
+                    Ok(None)
+                } else if resolved.len() == 1 {
+                    Ok(resolved.pop().map(|value| value.value))
+                } else {
+                    concatenate(resolved, path).map(Some)
+                }
+            }
+            HoconValue::Substitution {
+                path: target,
+                optional,
+                span,
+            } => match self.lookup(target)? {
+                Some(value) => Ok(Some(value)),
+                None if *optional => Ok(None),
+                None => Err(HoconError::UnresolvedSubstitution {
+                    path: target.to_string(),
+                    span: *span,
+                }),
+            },
+            value => Ok(Some(value.clone())),
+        }
+    }
+
+    /// Resolves the elements of an array or value concatenation, dropping optional substitutions which can't be found.
+    fn resolve_elements(
+        &mut self,
+        values: &[Spanned<HoconValue<'a>>],
+        path: &HoconPath<'a>,
+    ) -> Result<Vec<Spanned<HoconValue<'a>>>, HoconError> {
+        let mut resolved = vec![];
+        for value in values {
+            if let Some(resolved_value) = self.resolve_value(value, path)? {
+                resolved.push(Spanned::new(resolved_value, value.span));
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Looks up the resolved value at the path from the root of the document.
+    ///
+    /// When the path is that of the field being resolved, the substitution is self-referential and only the fields
+    /// assigned before it are considered.
+    fn lookup(&mut self, target: &HoconPath<'a>) -> Result<Option<HoconValue<'a>>, HoconError> {
+        let root = self.root;
+        if let Some(&(_, before)) = self.stack.last().filter(|(path, _)| path == target) {
+            return self.lookup_in(root, &HoconPath::default(), &target.segments, Some(before));
+        }
+        if let Some(resolved) = self.resolved.get(&target.segments) {
+            return Ok(resolved.clone());
+        }
+        let resolved = self.lookup_in(root, &HoconPath::default(), &target.segments, None)?;
+        self.resolved.insert(target.segments.clone(), resolved.clone());
+        Ok(resolved)
+    }
+
+    fn lookup_in(
+        &mut self,
+        value: &HoconValue<'a>,
+        prefix: &HoconPath<'a>,
+        remaining: &[Cow<'a, str>],
+        before: Option<*const HoconValue<'a>>,
+    ) -> Result<Option<HoconValue<'a>>, HoconError> {
+        let (Some((key, rest)), HoconValue::HoconObject(fields)) = (remaining.split_first(), value) else {
+            return Ok(None);
+        };
+
+        let path = join(prefix, std::slice::from_ref(key));
+        let mut candidates: Vec<_> = fields
+            .iter()
+            .filter_map(|field| match field {
+                HoconField::KeyValue(field_key, value) if field_key.segments == [key.as_ref()] => Some(value),
+                _ => None,
+            })
+            .collect();
+        if let Some(position) = rest.is_empty().then_some(before).flatten().and_then(|before| {
+            candidates
+                .iter()
+                .position(|candidate| std::ptr::eq(&candidate.value, before))
+        }) {
+            candidates.truncate(position);
+        }
+
+        // Walk back from the last assignment, merging objects until a value which isn't an object resets the field.
+        let mut objects = vec![];
+        for candidate in candidates.into_iter().rev() {
+            let found = match &candidate.value {
+                _ if rest.is_empty() => self.resolve_at(candidate, path.clone())?,
+                HoconValue::HoconObject(_) => self.lookup_in(candidate, &path, rest, before)?,
+                // Resolving the whole object would be a cycle, even when the requested field doesn't depend on it.
+                HoconValue::Substitution { path: target, .. } if self.is_resolving(self.root, &target.segments) => {
+                    let segments: Vec<_> = target.segments.iter().chain(rest).cloned().collect();
+                    self.stack.push((join(&path, rest), &candidate.value));
+                    let found = self.lookup_in(self.root, &HoconPath::default(), &segments, None);
+                    self.stack.pop();
+                    found?
+                }
+                _ => match self.resolve_at(candidate, path.clone())? {
+                    Some(resolved @ HoconValue::HoconObject(_)) => self.lookup_in(&resolved, &path, rest, before)?,
+                    Some(_) => break,
+                    None => None,
+                },
+            };
+
+            // Fields which turn out to be undefined optional substitutions are skipped in favour of earlier ones.
+            match found {
+                Some(HoconValue::HoconObject(fields)) => objects.push(fields),
+                Some(value) if objects.is_empty() => return Ok(Some(value)),
+                Some(_) => break,
+                None => {}
+            }
+        }
+
+        Ok(objects
+            .into_iter()
+            .rev()
+            .reduce(|mut merged, fields| {
+                merge_objects(&mut merged, fields);
+                merged
+            })
+            .map(HoconValue::HoconObject))
+    }
+
+    /// Whether an object assigned to the path within the value is currently being resolved, in which case its fields
+    /// are looked up one by one rather than resolving the object as a whole.
+    fn is_resolving(&self, value: &HoconValue<'a>, segments: &[Cow<'a, str>]) -> bool {
+        let Some((key, rest)) = segments.split_first() else {
+            return matches!(value, HoconValue::HoconObject(_))
+                && self.stack.iter().any(|(_, resolving)| std::ptr::eq(*resolving, value));
+        };
+        let HoconValue::HoconObject(fields) = value else {
+            return false;
+        };
+        fields.iter().any(|field| match field {
+            HoconField::KeyValue(field_key, value) => {
+                field_key.segments == [key.as_ref()] && self.is_resolving(value, rest)
+            }
+            _ => false,
+        })
+    }
+}
+
+fn join<'a>(prefix: &HoconPath<'a>, segments: &[Cow<'a, str>]) -> HoconPath<'a> {
+    HoconPath::new(prefix.segments.iter().chain(segments).cloned().collect())
+}
+
+/// Evaluates a value concatenation of which all substitutions have been resolved.
+fn concatenate<'a>(values: Vec<Spanned<HoconValue<'a>>>, path: &HoconPath<'a>) -> Result<HoconValue<'a>, HoconError> {
+    let expected = values
+        .iter()
+        .find(|value| !is_whitespace(value))
+        .map_or("string", |value| match &value.value {
+            HoconValue::HoconArray(_) | HoconValue::HoconObject(_) => type_name(value),
+            _ => "string",
+        });
+    let mismatch = |found: &HoconValue<'_>| HoconError::InvalidConcatenation {
+        path: path.to_string(),
+        expected,
+        found: type_name(found),
+    };
+
+    match expected {
+        "array" => {
+            let mut elements = vec![];
+            for value in values.into_iter().filter(|value| !is_whitespace(value)) {
+                match value.value {
+                    HoconValue::HoconArray(values) => elements.extend(values),
+                    other => return Err(mismatch(&other)),
+                }
+            }
+            Ok(HoconValue::HoconArray(elements))
+        }
+        "object" => {
+            let mut fields = vec![];
+            for value in values.into_iter().filter(|value| !is_whitespace(value)) {
+                match value.value {
+                    HoconValue::HoconObject(new_fields) => merge_objects(&mut fields, new_fields),
+                    other => return Err(mismatch(&other)),
+                }
+            }
+            Ok(HoconValue::HoconObject(fields))
+        }
+        _ => {
+            let mut concatenated = String::new();
+            for value in &values {
+                match &value.value {
+                    HoconValue::HoconString(HoconString::Quoted(s)) => concatenated.push_str(s),
+                    HoconValue::HoconString(HoconString::Unqouted(s) | HoconString::Multiline(s)) => {
+                        concatenated.push_str(s)
+                    }
+                    HoconValue::Int(_, literal) | HoconValue::Float(_, literal) | HoconValue::BigInt(literal) => {
+                        concatenated.push_str(literal)
+                    }
+                    HoconValue::HoconBoolean(b) => concatenated.push_str(&b.to_string()),
+                    HoconValue::HoconNull => concatenated.push_str("null"),
+                    other => return Err(mismatch(other)),
+                }
+            }
+            Ok(HoconValue::HoconString(HoconString::Quoted(Cow::Owned(concatenated))))
+        }
+    }
+}
+
+fn type_name(value: &HoconValue<'_>) -> &'static str {
+    match value {
+        HoconValue::HoconObject(_) => "object",
+        HoconValue::HoconArray(_) => "array",
+        HoconValue::HoconString(_) => "string",
+        HoconValue::Int(..) | HoconValue::Float(..) | HoconValue::BigInt(_) => "number",
+        HoconValue::HoconBoolean(_) => "boolean",
+        HoconValue::HoconNull => "null",
+        HoconValue::HoconInclude(_) => "include",
+        HoconValue::Substitution { .. } => "substitution",
+        HoconValue::Concatenation(_) => "concatenation",
+    }
+}
+
+fn is_whitespace(value: &HoconValue<'_>) -> bool {
+    matches!(value, HoconValue::HoconString(HoconString::Unqouted(s)) if s.chars().all(char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::parse, span::Span};
+
+    fn parse_resolved(input: &str) -> Result<HoconValue<'_>, HoconError> {
+        resolve(parse(input).unwrap())
+    }
+
+    fn field<'a>(key: &'a str, value: HoconValue<'a>) -> HoconField<'a> {
+        HoconField::KeyValue(HoconPath::from([key]), value.into())
+    }
+
+    #[test]
+    fn test_resolve_simple_substitution() {
+        let content = r#"
+            a = 1
+            b = ${a}
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::Int(1, "1".into())),
+            field("b", HoconValue::Int(1, "1".into())),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_resolve_nested_path_from_root() {
+        let content = r#"
+            server { port = 8080 }
+            client { target = ${server.port} }
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field(
+                "server",
+                HoconValue::HoconObject(vec![field("port", HoconValue::Int(8080, "8080".into()))]),
+            ),
+            field(
+                "client",
+                HoconValue::HoconObject(vec![field("target", HoconValue::Int(8080, "8080".into()))]),
+            ),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_resolve_forward_reference_chain() {
+        let content = r#"
+            a = ${b}
+            b = ${c.d}
+            c.d = done
+        "#;
+        let resolved = parse_resolved(content).unwrap();
+        let HoconValue::HoconObject(fields) = resolved else {
+            panic!("Expected object");
+        };
+        assert_eq!(
+            fields[0],
+            field("a", HoconValue::HoconString(HoconString::Unqouted("done".into())))
+        );
+        assert_eq!(
+            fields[1],
+            field("b", HoconValue::HoconString(HoconString::Unqouted("done".into())))
+        );
+    }
+
+    #[test]
+    fn test_resolve_object_substitution() {
+        let content = r#"
+            defaults { retries = 3 }
+            service = ${defaults}
+            retries = ${service.retries}
+        "#;
+        let defaults = HoconValue::HoconObject(vec![field("retries", HoconValue::Int(3, "3".into()))]);
+        let expected = HoconValue::HoconObject(vec![
+            field("defaults", defaults.clone()),
+            field("service", defaults),
+            field("retries", HoconValue::Int(3, "3".into())),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_resolve_in_array_and_concatenation() {
+        let content = r#"
+            a = x
+            list = [${a}, ${?missing}, y]
+            concat = ${a} ${?missing}
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::HoconString(HoconString::Unqouted("x".into()))),
+            field(
+                "list",
+                HoconValue::HoconArray(vec![
+                    HoconValue::HoconString(HoconString::Unqouted("x".into())).into(),
+                    HoconValue::HoconString(HoconString::Unqouted("y".into())).into(),
+                ]),
+            ),
+            field("concat", HoconValue::HoconString(HoconString::Quoted("x ".into()))),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_optional_substitution_removes_field() {
+        let content = r#"
+            a = ${?missing}
+            b = ${?missing} ${?other}
+            c = 1
+        "#;
+        let expected = HoconValue::HoconObject(vec![field("c", HoconValue::Int(1, "1".into()))]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_optional_substitution_keeps_previous_value() {
+        let content = r#"
+            a = 1
+            a = ${?missing}
+            b = ${a}
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::Int(1, "1".into())),
+            field("b", HoconValue::Int(1, "1".into())),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_unresolved_substitution() {
+        assert_eq!(
+            parse_resolved("a = ${missing.value}"),
+            Err(HoconError::UnresolvedSubstitution {
+                path: "missing.value".to_string(),
+                span: Span { start: 4, end: 20 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_substitution_into_non_object() {
+        assert_eq!(
+            parse_resolved("a = 1\nb = ${a.c}"),
+            Err(HoconError::UnresolvedSubstitution {
+                path: "a.c".to_string(),
+                span: Span { start: 10, end: 16 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_substitution_cycle() {
+        let content = r#"
+            a = ${b}
+            b = ${c}
+            c = ${a}
+        "#;
+        assert_eq!(
+            parse_resolved(content),
+            Err(HoconError::SubstitutionCycle {
+                chain: vec!["a".to_string(), "b".to_string(), "c".to_string(), "a".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_substitution_cycle_through_nested_paths() {
+        let content = r#"
+            x { a = ${y.b} }
+            y { b = ${x.a} }
+        "#;
+        assert_eq!(
+            parse_resolved(content),
+            Err(HoconError::SubstitutionCycle {
+                chain: vec!["x.a".to_string(), "y.b".to_string(), "x.a".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_substitution_of_containing_object() {
+        assert_eq!(
+            parse_resolved("a { b = ${a} }"),
+            Err(HoconError::SubstitutionCycle {
+                chain: vec!["a".to_string(), "a.b".to_string(), "a".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_substitution_of_sibling_in_same_object() {
+        let content = "a { b = 1, c = ${a.b} }";
+        let expected = HoconValue::HoconObject(vec![field(
+            "a",
+            HoconValue::HoconObject(vec![
+                field("b", HoconValue::Int(1, "1".into())),
+                field("c", HoconValue::Int(1, "1".into())),
+            ]),
+        )]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_substitution_into_object_being_resolved() {
+        // `b.x` only depends on `a.x`, so it resolves even though `a` is being resolved when it is looked up.
+        let content = "a { x = 1, y = ${b.x} }\nb = ${a}";
+        let object = HoconValue::HoconObject(vec![
+            field("x", HoconValue::Int(1, "1".into())),
+            field("y", HoconValue::Int(1, "1".into())),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field("a", object.clone()), field("b", object)]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+
+        assert_eq!(
+            parse_resolved("a { y = ${b.y} }\nb = ${a}"),
+            Err(HoconError::SubstitutionCycle {
+                chain: vec!["a.y".to_string(), "b.y".to_string(), "a.y".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_cycle_error_message() {
+        let error = HoconError::SubstitutionCycle {
+            chain: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        };
+        assert_eq!(error.to_string(), "Substitution cycle: a -> b -> a");
+    }
+
+    #[test]
+    fn test_substitution_fan_out_is_resolved_once() {
+        // Each field refers to the previous one twice, which takes exponential time unless lookups are remembered.
+        let depth = 20;
+        let mut content = "a0 = x\n".to_string();
+        for i in 1..=depth {
+            content.push_str(&format!("a{i} = ${{a{}}}${{a{}}}\n", i - 1, i - 1));
+        }
+        content.push_str("a0 = y");
+
+        let HoconValue::HoconObject(fields) = parse_resolved(&content).unwrap() else {
+            panic!("Expected an object");
+        };
+        let HoconField::KeyValue(_, value) = &fields[depth] else {
+            panic!("Expected a field");
+        };
+        let expected = HoconValue::HoconString(HoconString::Quoted("y".repeat(1 << depth).into()));
+        assert_eq!(value.value, expected);
+    }
+
+    #[test]
+    fn test_self_reference_uses_previous_value() {
+        let content = r#"
+            path = "/bin"
+            path = ${path}":/extra"
+        "#;
+        let expected = HoconValue::HoconObject(vec![field(
+            "path",
+            HoconValue::HoconString(HoconString::Quoted("/bin:/extra".into())),
+        )]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_self_reference_in_nested_object() {
+        let content = r#"
+            server { name = a }
+            server { name = ${server.name}"-b" }
+            other = ${server.name}
+        "#;
+        let concatenated = HoconValue::HoconString(HoconString::Quoted("a-b".into()));
+        let expected = HoconValue::HoconObject(vec![
+            field(
+                "server",
+                HoconValue::HoconObject(vec![field("name", concatenated.clone())]),
+            ),
+            field("other", concatenated),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_self_reference_without_previous_value() {
+        assert_eq!(
+            parse_resolved("a = ${a}"),
+            Err(HoconError::UnresolvedSubstitution {
+                path: "a".to_string(),
+                span: Span { start: 4, end: 8 },
+            })
+        );
+        assert_eq!(
+            parse_resolved("a = ${?a}\nb = 1"),
+            Ok(HoconValue::HoconObject(vec![field(
+                "b",
+                HoconValue::Int(1, "1".into())
+            )]))
+        );
+    }
+
+    #[test]
+    fn test_append_to_previous_array() {
+        let content = r#"
+            plugins = [core]
+            plugins += "foo"
+        "#;
+        let expected = HoconValue::HoconObject(vec![field(
+            "plugins",
+            HoconValue::HoconArray(vec![
+                HoconValue::HoconString(HoconString::Unqouted("core".into())).into(),
+                HoconValue::HoconString(HoconString::Quoted("foo".into())).into(),
+            ]),
+        )]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_append_without_previous_value() {
+        let expected = HoconValue::HoconObject(vec![field(
+            "plugins",
+            HoconValue::HoconArray(vec![HoconValue::HoconString(HoconString::Quoted("foo".into())).into()]),
+        )]);
+        assert_eq!(parse_resolved(r#"plugins += "foo""#), Ok(expected));
+    }
+
+    #[test]
+    fn test_append_in_nested_object() {
+        let content = r#"
+            app.plugins = [core]
+            app { plugins += extra }
+        "#;
+        let HoconValue::HoconObject(fields) = parse_resolved(content).unwrap() else {
+            panic!("Expected object");
+        };
+        let expected = field(
+            "app",
+            HoconValue::HoconObject(vec![field(
+                "plugins",
+                HoconValue::HoconArray(vec![
+                    HoconValue::HoconString(HoconString::Unqouted("core".into())).into(),
+                    HoconValue::HoconString(HoconString::Unqouted("extra".into())).into(),
+                ]),
+            )]),
+        );
+        assert_eq!(fields, vec![expected]);
+    }
+
+    #[test]
+    fn test_append_in_array_element() {
+        let expected = HoconValue::HoconObject(vec![field(
+            "list",
+            HoconValue::HoconArray(vec![HoconValue::HoconObject(vec![field(
+                "a",
+                HoconValue::HoconArray(vec![HoconValue::Int(1, "1".into()).into()]),
+            )])
+            .into()]),
+        )]);
+        assert_eq!(parse_resolved("list = [ { a += 1 } ]"), Ok(expected));
+    }
+
+    #[test]
+    fn test_reference_to_other_field_is_not_self_referential() {
+        let content = r#"
+            a = ${b}
+            b = ${a}
+        "#;
+        assert_eq!(
+            parse_resolved(content),
+            Err(HoconError::SubstitutionCycle {
+                chain: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_concatenate_simple_values() {
+        let content = r#"
+            greeting = hello   "big"  world 42 true
+            version = 1.0 . ${minor}
+            minor = 02
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field(
+                "greeting",
+                HoconValue::HoconString(HoconString::Quoted("hello   big  world 42 true".into())),
+            ),
+            field(
+                "version",
+                HoconValue::HoconString(HoconString::Quoted("1.0 . 02".into())),
+            ),
+            field("minor", HoconValue::Int(2, "02".into())),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_concatenate_arrays() {
+        let content = r#"
+            base = [1, 2]
+            list = ${base} [3] [4]
+        "#;
+        let HoconValue::HoconObject(fields) = parse_resolved(content).unwrap() else {
+            panic!("Expected object");
+        };
+        assert_eq!(
+            fields[1],
+            field(
+                "list",
+                HoconValue::HoconArray(vec![
+                    HoconValue::Int(1, "1".into()).into(),
+                    HoconValue::Int(2, "2".into()).into(),
+                    HoconValue::Int(3, "3".into()).into(),
+                    HoconValue::Int(4, "4".into()).into(),
+                ]),
+            )
+        );
+    }
+
+    #[test]
+    fn test_concatenate_objects() {
+        let content = r#"
+            defaults { host = localhost, port = 80 }
+            server = ${defaults} { port = 8080 } { tls { enabled = true } }
+        "#;
+        let HoconValue::HoconObject(fields) = parse_resolved(content).unwrap() else {
+            panic!("Expected object");
+        };
+        assert_eq!(
+            fields[1],
+            field(
+                "server",
+                HoconValue::HoconObject(vec![
+                    field(
+                        "host",
+                        HoconValue::HoconString(HoconString::Unqouted("localhost".into()))
+                    ),
+                    field("port", HoconValue::Int(8080, "8080".into())),
+                    field(
+                        "tls",
+                        HoconValue::HoconObject(vec![field("enabled", HoconValue::HoconBoolean(true))]),
+                    ),
+                ]),
+            )
+        );
+    }
+
+    #[test]
+    fn test_concatenate_mixed_types() {
+        assert_eq!(
+            parse_resolved("a { b = [1] x }"),
+            Err(HoconError::InvalidConcatenation {
+                path: "a.b".to_string(),
+                expected: "array",
+                found: "string",
+            })
+        );
+        assert_eq!(
+            parse_resolved("a = x { b = 1 }"),
+            Err(HoconError::InvalidConcatenation {
+                path: "a".to_string(),
+                expected: "string",
+                found: "object",
+            })
+        );
+        assert_eq!(
+            parse_resolved("a = [1] x").unwrap_err().to_string(),
+            "Cannot concatenate string with array at a"
+        );
+    }
+
+    #[test]
+    fn test_merge_duplicate_objects_deeply() {
+        let content = r#"
+            a.b.c = 1
+            a { b { d = 2 }, e = 3 }
+            a.b { c = 4 }
+        "#;
+        let expected = HoconValue::HoconObject(vec![field(
+            "a",
+            HoconValue::HoconObject(vec![
+                field(
+                    "b",
+                    HoconValue::HoconObject(vec![
+                        field("c", HoconValue::Int(4, "4".into())),
+                        field("d", HoconValue::Int(2, "2".into())),
+                    ]),
+                ),
+                field("e", HoconValue::Int(3, "3".into())),
+            ]),
+        )]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_later_value_overrides_earlier() {
+        let content = r#"
+            a = 1
+            a = [2]
+            b { c = 1 }
+            b = 2
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field("a", HoconValue::HoconArray(vec![HoconValue::Int(2, "2".into()).into()])),
+            field("b", HoconValue::Int(2, "2".into())),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_override_then_merge_again() {
+        let content = r#"
+            a { x = 1 }
+            a = null
+            a { y = 2 }
+            a { z = 3 }
+            b = ${a}
+        "#;
+        let merged = HoconValue::HoconObject(vec![
+            field("y", HoconValue::Int(2, "2".into())),
+            field("z", HoconValue::Int(3, "3".into())),
+        ]);
+        let expected = HoconValue::HoconObject(vec![field("a", merged.clone()), field("b", merged)]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_merge_object_from_substitution() {
+        let content = r#"
+            defaults { port = 80, tls = false }
+            server { host = localhost, port = 1 }
+            server = ${defaults}
+            server { port = 8080 }
+            host = ${server.host}
+            port = ${server.port}
+        "#;
+        let expected = HoconValue::HoconObject(vec![
+            field(
+                "defaults",
+                HoconValue::HoconObject(vec![
+                    field("port", HoconValue::Int(80, "80".into())),
+                    field("tls", HoconValue::HoconBoolean(false)),
+                ]),
+            ),
+            field(
+                "server",
+                HoconValue::HoconObject(vec![
+                    field(
+                        "host",
+                        HoconValue::HoconString(HoconString::Unqouted("localhost".into())),
+                    ),
+                    field("port", HoconValue::Int(8080, "8080".into())),
+                    field("tls", HoconValue::HoconBoolean(false)),
+                ]),
+            ),
+            field(
+                "host",
+                HoconValue::HoconString(HoconString::Unqouted("localhost".into())),
+            ),
+            field("port", HoconValue::Int(8080, "8080".into())),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+
+    #[test]
+    fn test_number_with_unit_is_a_string() {
+        let content = r#"
+            timeout = 10s
+            memory = [512MiB, 1.5G]
+            version = 1.2.3
+        "#;
+        let string = |value: &'static str| HoconValue::HoconString(HoconString::Quoted(value.into()));
+        let expected = HoconValue::HoconObject(vec![
+            field("timeout", string("10s")),
+            field(
+                "memory",
+                HoconValue::HoconArray(vec![string("512MiB").into(), string("1.5G").into()]),
+            ),
+            field("version", string("1.2.3")),
+        ]);
+        assert_eq!(parse_resolved(content), Ok(expected));
+    }
+}
@@ -1,13 +1,23 @@
 use core::fmt;
+use std::{borrow::Cow, str::FromStr};
 
-use nom_language::error::VerboseError;
 use serde::de::{self, DeserializeOwned, Deserializer, MapAccess, Visitor};
 
 use crate::parser::{HoconError, HoconField, HoconString, HoconValue};
 
 impl serde::de::Error for HoconError {
     fn custom<T: fmt::Display>(e: T) -> Self {
-        HoconError::ParseError { msg: e.to_string() }
+        HoconError::DeserializeError { msg: e.to_string() }
+    }
+}
+
+fn visit_cow_str<'de, V>(value: Cow<'de, str>, visitor: V) -> Result<V::Value, HoconError>
+where
+    V: Visitor<'de>,
+{
+    match value {
+        Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+        Cow::Owned(value) => visitor.visit_string(value),
     }
 }
 
@@ -37,13 +47,20 @@ impl<'de, 'a> MapAccess<'de> for HoconObjectIter<'a, 'de> {
                     self.first = false;
                 }
 
-                if elements.is_empty() {
-                    Ok(None)
-                } else {
-                    seed.deserialize(&mut *self.de).map(Some)
+                match elements.first() {
+                    None => Ok(None),
+                    Some(HoconField::KeyValue(path, _)) if path.segments.len() == 1 => {
+                        let mut key_deser = HoconDeserializer {
+                            input: HoconValue::HoconString(HoconString::Quoted(path.segments[0].clone())),
+                        };
+                        seed.deserialize(&mut key_deser).map(Some)
+                    }
+                    Some(_) => Err(HoconError::DeserializeError {
+                        msg: "Expected a single key".to_owned(),
+                    }),
                 }
             }
-            _ => Err(HoconError::ParseError {
+            _ => Err(HoconError::DeserializeError {
                 msg: "Expected object type".to_owned(),
             }),
         }
@@ -57,16 +74,16 @@ impl<'de, 'a> MapAccess<'de> for HoconObjectIter<'a, 'de> {
             HoconValue::HoconObject(map) => {
                 if let Some(HoconField::KeyValue(_, value)) = map.first() {
                     let mut value_deser = HoconDeserializer {
-                        input: value.to_owned(),
+                        input: value.value.clone(),
                     };
                     seed.deserialize(&mut value_deser)
                 } else {
-                    Err(HoconError::ParseError {
+                    Err(HoconError::DeserializeError {
                         msg: "Expected non-empty map".to_owned(),
                     })
                 }
             }
-            _ => Err(HoconError::ParseError {
+            _ => Err(HoconError::DeserializeError {
                 msg: "Expcected object type".to_owned(),
             }),
         }
@@ -78,9 +95,41 @@ pub struct HoconDeserializer<'de> {
 }
 
 impl<'de> HoconDeserializer<'de> {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &'de str) -> Result<Self, HoconError> {
-        let input = crate::parser::parse::<VerboseError<&'de str>>(input)?;
-        Ok(HoconDeserializer { input })
+        let input = crate::parser::parse(input)?;
+        Ok(HoconDeserializer {
+            input: crate::resolve::resolve(input)?,
+        })
+    }
+}
+
+impl HoconDeserializer<'_> {
+    /// Reads an integer without going through a floating point number, so no precision is lost.
+    fn integer<T: TryFrom<i64> + FromStr>(&self) -> Result<T, HoconError> {
+        let out_of_range = |literal: &str| HoconError::DeserializeError {
+            msg: format!("Integer {literal} is out of range"),
+        };
+        match &self.input {
+            HoconValue::Int(value, literal) => T::try_from(*value).map_err(|_| out_of_range(literal)),
+            HoconValue::BigInt(literal) => literal.parse().map_err(|_| out_of_range(literal)),
+            _ => Err(HoconError::DeserializeError {
+                msg: "Expected integer type".to_owned(),
+            }),
+        }
+    }
+
+    fn float(&self) -> Result<f64, HoconError> {
+        match &self.input {
+            HoconValue::Float(value, _) => Ok(*value),
+            HoconValue::Int(value, _) => Ok(*value as f64),
+            HoconValue::BigInt(literal) => literal.parse().map_err(|_| HoconError::DeserializeError {
+                msg: format!("Number {literal} is out of range"),
+            }),
+            _ => Err(HoconError::DeserializeError {
+                msg: "Expected number type".to_owned(),
+            }),
+        }
     }
 }
 
@@ -94,7 +143,7 @@ where
     T::deserialize(&mut deserializer)
 }
 
-impl<'de, 'a> Deserializer<'de> for &'a mut HoconDeserializer<'de> {
+impl<'de> Deserializer<'de> for &mut HoconDeserializer<'de> {
     type Error = HoconError;
 
     fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -111,74 +160,88 @@ impl<'de, 'a> Deserializer<'de> for &'a mut HoconDeserializer<'de> {
         todo!()
     }
 
-    fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_i8(self.integer()?)
     }
 
-    fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_i16(self.integer()?)
     }
 
-    fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_i32(self.integer()?)
     }
 
-    fn deserialize_i64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_i64(self.integer()?)
     }
 
-    fn deserialize_u8<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_i128(self.integer()?)
     }
 
-    fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_u8(self.integer()?)
     }
 
-    fn deserialize_u32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_u16(self.integer()?)
     }
 
-    fn deserialize_u64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_u32(self.integer()?)
     }
 
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_u64(self.integer()?)
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_u128(self.integer()?)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f32(self.float()? as f32)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f64(self.float()?)
     }
 
     fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -199,10 +262,11 @@ impl<'de, 'a> Deserializer<'de> for &'a mut HoconDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match self.input {
-            HoconValue::HoconString(HoconString::Quoted(value)) => visitor.visit_borrowed_str(value),
-            HoconValue::HoconString(HoconString::Unqouted(value)) => visitor.visit_borrowed_str(value),
-            _ => Err(HoconError::ParseError {
+        match &self.input {
+            HoconValue::HoconString(
+                HoconString::Quoted(value) | HoconString::Unqouted(value) | HoconString::Multiline(value),
+            ) => visit_cow_str(value.clone(), visitor),
+            _ => Err(HoconError::DeserializeError {
                 msg: "Expected string type".to_owned(),
             }),
         }
@@ -280,7 +344,7 @@ impl<'de, 'a> Deserializer<'de> for &'a mut HoconDeserializer<'de> {
                 let object_iter = HoconObjectIter::new(self);
                 visitor.visit_map(object_iter)
             }
-            _ => Err(HoconError::ParseError {
+            _ => Err(HoconError::DeserializeError {
                 msg: "Expected object type".to_owned(),
             }),
         }
@@ -314,17 +378,7 @@ impl<'de, 'a> Deserializer<'de> for &'a mut HoconDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match &mut self.input {
-            HoconValue::HoconObject(ref mut map) => match map.first().take().map(|s| s.to_owned()) {
-                Some(HoconField::KeyValue(key, _)) => visitor.visit_borrowed_str(key),
-                _ => Err(HoconError::ParseError {
-                    msg: "Expected non-empty object".to_owned(),
-                }),
-            },
-            _ => Err(HoconError::ParseError {
-                msg: "Expected object type".to_owned(),
-            }),
-        }
+        self.deserialize_string(visitor)
     }
 
     fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -338,8 +392,11 @@ impl<'de, 'a> Deserializer<'de> for &'a mut HoconDeserializer<'de> {
 #[cfg(test)]
 mod tests {
 
+    use indexmap::IndexMap;
     use serde::Deserialize;
 
+    use crate::parser::HoconError;
+
     #[derive(Deserialize, Debug, PartialEq)]
     struct TestStruct {
         hello: String,
@@ -358,4 +415,195 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_deserialize_escaped_strings() {
+        let s = r#"{ hello = "wor\"ld", world = "\u00e9" }"#;
+        let t: TestStruct = super::from_str(s).unwrap();
+        assert_eq!(
+            t,
+            TestStruct {
+                hello: "wor\"ld".to_string(),
+                world: "é".to_string()
+            }
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Outer {
+        inner: TestStruct,
+    }
+
+    #[test]
+    fn test_deserialize_dotted_keys() {
+        let s = r#"
+            inner.hello = "world"
+            inner { world = "hello" }
+        "#;
+        let t: Outer = super::from_str(s).unwrap();
+        assert_eq!(
+            t,
+            Outer {
+                inner: TestStruct {
+                    hello: "world".to_string(),
+                    world: "hello".to_string()
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_substitutions() {
+        let s = r#"
+            greeting = "world"
+            hello = ${greeting}
+            world = ${?missing}
+            world = "hello"
+        "#;
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct WithGreeting {
+            greeting: String,
+            hello: String,
+            world: String,
+        }
+        let t: WithGreeting = super::from_str(s).unwrap();
+        assert_eq!(
+            t,
+            WithGreeting {
+                greeting: "world".to_string(),
+                hello: "world".to_string(),
+                world: "hello".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_concatenation() {
+        let s = r#"
+            name = bar baz 42
+            greeting = "hello "${name}
+        "#;
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Concatenated {
+            name: String,
+            greeting: String,
+        }
+        let t: Concatenated = super::from_str(s).unwrap();
+        assert_eq!(
+            t,
+            Concatenated {
+                name: "bar baz 42".to_string(),
+                greeting: "hello bar baz 42".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_duplicate_keys() {
+        let s = r#"
+            name = first
+            inner { hello = "world", world = "hello" }
+            inner.hello = "there"
+            name = second
+        "#;
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Duplicates {
+            name: String,
+            inner: TestStruct,
+        }
+        let t: Duplicates = super::from_str(s).unwrap();
+        assert_eq!(
+            t,
+            Duplicates {
+                name: "second".to_string(),
+                inner: TestStruct {
+                    hello: "there".to_string(),
+                    world: "hello".to_string(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_numbers() {
+        let s = r#"
+            id = 9007199254740993
+            unsigned = 18446744073709551615
+            small = -12
+            ratio = 0.25
+            whole = 3
+        "#;
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Numbers {
+            id: i64,
+            unsigned: u64,
+            small: i8,
+            ratio: f32,
+            whole: f64,
+        }
+        let t: Numbers = super::from_str(s).unwrap();
+        assert_eq!(
+            t,
+            Numbers {
+                id: 9007199254740993,
+                unsigned: u64::MAX,
+                small: -12,
+                ratio: 0.25,
+                whole: 3.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_integer_out_of_range() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Small {
+            value: u8,
+        }
+        match super::from_str::<Small>("value = 256") {
+            Err(HoconError::DeserializeError { msg }) => assert_eq!(msg, "Integer 256 is out of range"),
+            other => panic!("Expected out of range error, got {other:?}"),
+        }
+        assert!(super::from_str::<Small>("value = -1").is_err());
+        assert!(super::from_str::<Small>("value = 1.5").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_map_in_file_order() {
+        let s = r#"
+            zulu = 1
+            alpha = 2
+            mike = ${alpha}
+            alpha = 4
+            bravo = 5
+        "#;
+        let t: IndexMap<String, i64> = super::from_str(s).unwrap();
+        let entries: Vec<_> = t.iter().map(|(key, value)| (key.as_str(), *value)).collect();
+        assert_eq!(entries, vec![("zulu", 1), ("alpha", 4), ("mike", 4), ("bravo", 5)]);
+    }
+
+    #[test]
+    fn test_deserialize_nested_map_in_file_order() {
+        let s = r#"
+            servers { web { port = 80 }, db { port = 5432 } }
+            servers.cache.port = 6379
+            servers { web.port = 8080 }
+        "#;
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Server {
+            port: u16,
+        }
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Servers {
+            servers: IndexMap<String, Server>,
+        }
+        let t: Servers = super::from_str(s).unwrap();
+        let ports: Vec<_> = t
+            .servers
+            .iter()
+            .map(|(name, server)| (name.as_str(), server.port))
+            .collect();
+        assert_eq!(ports, vec![("web", 8080), ("db", 5432), ("cache", 6379)]);
+    }
 }
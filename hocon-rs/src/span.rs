@@ -0,0 +1,260 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// A range of bytes within the parsed document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates the smallest span covering both this span and the other one.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Moves the span forward by the given number of bytes.
+    pub fn shifted(self, offset: usize) -> Span {
+        Span {
+            start: self.start + offset,
+            end: self.end + offset,
+        }
+    }
+}
+
+/// A node of the AST along with the location of the source text it was parsed from.
+///
+/// The span is ignored when comparing nodes, two ASTs are equal when they have the same structure and values
+/// regardless of how they were formatted.
+#[derive(Clone, Debug)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Spanned { value, span }
+    }
+
+    /// Transforms the node while keeping its location.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned::new(f(self.value), self.span)
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    /// Wraps a node which wasn't parsed from a document, using an empty span.
+    fn from(value: T) -> Self {
+        Spanned::new(value, Span::default())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+/// The unit in which the column of a [`Position`] is counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// Bytes of the UTF-8 encoded line.
+    Utf8,
+    /// UTF-16 code units, as used by the language server protocol.
+    Utf16,
+}
+
+/// A zero based line and column within a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Converts a byte offset within the source into a position. Offsets beyond the end of the source, or within a
+    /// multi-byte character, are moved back to the closest preceding character.
+    pub fn from_offset(source: &str, offset: usize, unit: ColumnUnit) -> Position {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        let text = &before[line_start..];
+        Position {
+            line: before.bytes().filter(|&b| b == b'\n').count(),
+            column: match unit {
+                ColumnUnit::Utf8 => text.len(),
+                ColumnUnit::Utf16 => text.encode_utf16().count(),
+            },
+        }
+    }
+}
+
+/// The documents a configuration was loaded from, which tells apart the spans of values from different documents.
+///
+/// Each document is given its own range of offsets, of which the start is the offset its spans must be moved by.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    sources: Vec<Source>,
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    name: Option<String>,
+    start: usize,
+    text: String,
+}
+
+impl SourceMap {
+    /// Adds a document, returning the offset its spans must be moved by.
+    pub fn add(&mut self, name: Option<String>, text: String) -> usize {
+        // The ranges are one past the end of the document, so a span at the very end stays within it.
+        let start = self
+            .sources
+            .last()
+            .map_or(0, |source| source.start + source.text.len() + 1);
+        self.sources.push(Source { name, start, text });
+        start
+    }
+
+    /// Where the span is located, or only the first document when there is no span.
+    pub fn origin(&self, span: Option<Span>) -> ConfigOrigin {
+        let Some(span) = span else {
+            return ConfigOrigin {
+                name: self.sources.first().and_then(|source| source.name.clone()),
+                position: None,
+            };
+        };
+        match self.sources.iter().rev().find(|source| source.start <= span.start) {
+            Some(source) => ConfigOrigin {
+                name: source.name.clone(),
+                position: Some(Position::from_offset(
+                    &source.text,
+                    span.start - source.start,
+                    ColumnUnit::Utf8,
+                )),
+            },
+            None => ConfigOrigin::default(),
+        }
+    }
+}
+
+/// Where a value of a configuration was defined: the name of its document, if it has one, and its position within it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigOrigin {
+    pub name: Option<String>,
+    pub position: Option<Position>,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, self.position) {
+            (Some(name), Some(position)) => write!(f, "{name}:{}:{}", position.line + 1, position.column + 1),
+            (Some(name), None) => f.write_str(name),
+            (None, Some(position)) => write!(f, "line {}, column {}", position.line + 1, position.column + 1),
+            (None, None) => f.write_str("document"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_from_offset() {
+        let source = "a = 1\nb = 2\n";
+        assert_eq!(
+            Position::from_offset(source, 0, ColumnUnit::Utf8),
+            Position { line: 0, column: 0 }
+        );
+        assert_eq!(
+            Position::from_offset(source, 10, ColumnUnit::Utf8),
+            Position { line: 1, column: 4 }
+        );
+        assert_eq!(
+            Position::from_offset(source, 12, ColumnUnit::Utf8),
+            Position { line: 2, column: 0 }
+        );
+    }
+
+    #[test]
+    fn test_position_from_offset_multi_byte() {
+        // 'é' takes 2 bytes in UTF-8 and 1 code unit in UTF-16, '😀' takes 4 bytes and 2 code units.
+        let source = "k = \"é😀\" x";
+        let offset = source.find('x').unwrap();
+        assert_eq!(
+            Position::from_offset(source, offset, ColumnUnit::Utf8),
+            Position { line: 0, column: 13 }
+        );
+        assert_eq!(
+            Position::from_offset(source, offset, ColumnUnit::Utf16),
+            Position { line: 0, column: 10 }
+        );
+    }
+
+    #[test]
+    fn test_position_from_offset_out_of_bounds() {
+        let source = "é";
+        assert_eq!(
+            Position::from_offset(source, 1, ColumnUnit::Utf8),
+            Position { line: 0, column: 0 }
+        );
+        assert_eq!(
+            Position::from_offset(source, 100, ColumnUnit::Utf16),
+            Position { line: 0, column: 1 }
+        );
+    }
+
+    #[test]
+    fn test_source_map_origin() {
+        let mut sources = SourceMap::default();
+        assert_eq!(sources.add(Some("a.conf".to_string()), "a = 1\nb = 2".to_string()), 0);
+        assert_eq!(sources.add(None, "c = 3".to_string()), 12);
+
+        let origin = sources.origin(Some(Span { start: 10, end: 11 }));
+        assert_eq!(origin.to_string(), "a.conf:2:5");
+        let origin = sources.origin(Some(Span { start: 16, end: 17 }));
+        assert_eq!(
+            origin,
+            ConfigOrigin {
+                name: None,
+                position: Some(Position { line: 0, column: 4 })
+            }
+        );
+        assert_eq!(origin.to_string(), "line 1, column 5");
+        assert_eq!(sources.origin(None).to_string(), "a.conf");
+        assert_eq!(SourceMap::default().origin(None).to_string(), "document");
+    }
+
+    #[test]
+    fn test_spanned_equality_ignores_span() {
+        let parsed = Spanned::new(1, Span { start: 4, end: 5 });
+        assert_eq!(parsed, Spanned::from(1));
+        assert_ne!(parsed, Spanned::from(2));
+    }
+}
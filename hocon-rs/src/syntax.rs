@@ -0,0 +1,656 @@
+//! A lossless concrete syntax tree of a document.
+//!
+//! Unlike the AST produced by [`crate::parser::parse`], the syntax tree keeps every byte of the document: whitespace,
+//! comments, separators and the original quoting of keys and values. Printing the tree yields exactly the text it
+//! was parsed from, which makes it suitable for formatters and for editing documents without disturbing the parts
+//! which weren't changed.
+
+use std::fmt;
+
+use nom::Parser;
+
+use crate::{
+    parser::{self, is_inline_whitespace, ConfigSyntax, DocumentError, HoconError, Input},
+    span::Span,
+};
+
+/// The kind of a [`SyntaxNode`], which groups the tokens and nodes making up a part of the document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The whole document, including the whitespace and comments around the root value.
+    Document,
+    /// An object, with or without braces.
+    Object,
+    Array,
+    /// A key along with its separator and value, or an include.
+    Field,
+    /// An include statement, either as a field or as a value.
+    Include,
+    /// A path expression, used as the key of a field or within a substitution.
+    Key,
+    /// A value, which consists of multiple values and the whitespace between them in case of a value concatenation.
+    Value,
+    Substitution,
+}
+
+/// The kind of a [`SyntaxToken`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Whitespace which doesn't include a newline.
+    Whitespace,
+    Newline,
+    /// A `//` or `#` comment, excluding the newline ending it.
+    Comment,
+    Comma,
+    Colon,
+    Equals,
+    PlusEquals,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    /// The start of a substitution, `${` or `${?`.
+    SubstitutionStart,
+    /// The `include` keyword or the kind of resource being included.
+    Keyword,
+    Null,
+    True,
+    False,
+    Number,
+    UnquotedString,
+    QuotedString,
+    MultilineString,
+}
+
+/// A piece of the document text, the leaves of the syntax tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxToken<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+/// A node of the syntax tree, of which the children cover its span without gaps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxNode<'a> {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub children: Vec<SyntaxElement<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(SyntaxToken<'a>),
+}
+
+impl<'a> SyntaxNode<'a> {
+    /// All tokens within this node, in document order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken<'a>> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'n>(&'n self, tokens: &mut Vec<&'n SyntaxToken<'a>>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    /// Writes the text of the node exactly as it appeared in the document.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{node}")?,
+                SyntaxElement::Token(token) => f.write_str(token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the given input as a Hocon document into a lossless syntax tree.
+pub fn parse(input: &str) -> Result<SyntaxNode<'_>, HoconError> {
+    parse_with_syntax(input, ConfigSyntax::Conf)
+}
+
+/// Parses the given input as a document in the given syntax into a lossless syntax tree.
+///
+/// The document is validated by the same parser as [`crate::parser::parse_with_syntax`], so it fails in exactly the
+/// same cases.
+///
+/// Java properties documents have no syntax tree, they result in [`HoconError::UnsupportedSyntax`].
+pub fn parse_with_syntax(input: &str, syntax: ConfigSyntax) -> Result<SyntaxNode<'_>, HoconError> {
+    parser::parse_with_syntax(input, syntax)?;
+    Ok(Builder {
+        source: input,
+        offset: 0,
+    }
+    .document())
+}
+
+/// Builds the syntax tree of a document which is known to be valid.
+///
+/// Tokens are recognized with the parsers used for the AST, so both agree on where each token ends. Should the
+/// builder not recognize the input, it is kept as an unquoted string so no text is ever lost.
+struct Builder<'a> {
+    source: &'a str,
+    offset: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn at(&self, text: &str) -> bool {
+        self.rest().starts_with(text)
+    }
+
+    /// The length of the text recognized by the parser at the current location.
+    fn recognize<O>(&self, mut parser: impl Parser<Input<'a>, Output = O, Error = DocumentError>) -> Option<usize> {
+        let (remainder, _) = parser.parse(Input::from(self.rest())).ok()?;
+        Some(remainder.location_offset()).filter(|&len| len > 0)
+    }
+
+    fn token(&mut self, kind: TokenKind, len: usize) -> SyntaxElement<'a> {
+        let span = Span {
+            start: self.offset,
+            end: self.offset + len,
+        };
+        self.offset = span.end;
+        SyntaxElement::Token(SyntaxToken {
+            kind,
+            text: &self.source[span.start..span.end],
+            span,
+        })
+    }
+
+    fn node(&self, kind: NodeKind, start: usize, children: Vec<SyntaxElement<'a>>) -> SyntaxElement<'a> {
+        SyntaxElement::Node(SyntaxNode {
+            kind,
+            span: Span {
+                start,
+                end: self.offset,
+            },
+            children,
+        })
+    }
+
+    /// Adds the whitespace, newlines and comments at the current location.
+    fn trivia(&mut self, children: &mut Vec<SyntaxElement<'a>>) {
+        loop {
+            let rest = self.rest();
+            let whitespace = rest.len() - rest.trim_start_matches(is_inline_whitespace).len();
+            if whitespace > 0 {
+                children.push(self.token(TokenKind::Whitespace, whitespace));
+            } else if self.at("\n") {
+                children.push(self.token(TokenKind::Newline, 1));
+            } else if let Some(len) = self.recognize(parser::comment) {
+                children.push(self.token(TokenKind::Comment, len));
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn document(mut self) -> SyntaxNode<'a> {
+        let mut children = vec![];
+        self.trivia(&mut children);
+        if self.at("[") {
+            children.push(self.array());
+        } else if self.at("{") {
+            children.push(self.object());
+        } else {
+            let start = self.offset;
+            let fields = self.elements(None, Self::field);
+            children.push(self.node(NodeKind::Object, start, fields));
+        }
+        // Anything after the root value is only whitespace, which is also kept in case of an unbraced object.
+        self.trivia(&mut children);
+        SyntaxNode {
+            kind: NodeKind::Document,
+            span: Span {
+                start: 0,
+                end: self.offset,
+            },
+            children,
+        }
+    }
+
+    /// Adds the elements of an object or array along with the separators and trivia between them, up to the closing
+    /// character or the end of the input.
+    fn elements(
+        &mut self,
+        closing: Option<char>,
+        element: fn(&mut Self) -> SyntaxElement<'a>,
+    ) -> Vec<SyntaxElement<'a>> {
+        let mut children = vec![];
+        loop {
+            self.trivia(&mut children);
+            match self.rest().chars().next() {
+                None => break,
+                Some(c) if Some(c) == closing => break,
+                Some(',') => children.push(self.token(TokenKind::Comma, 1)),
+                Some(_) => children.push(element(self)),
+            }
+        }
+        children
+    }
+
+    fn object(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        let mut children = vec![self.token(TokenKind::OpenBrace, 1)];
+        children.extend(self.elements(Some('}'), Self::field));
+        if self.at("}") {
+            children.push(self.token(TokenKind::CloseBrace, 1));
+        }
+        self.node(NodeKind::Object, start, children)
+    }
+
+    fn array(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        let mut children = vec![self.token(TokenKind::OpenBracket, 1)];
+        children.extend(self.elements(Some(']'), Self::value));
+        if self.at("]") {
+            children.push(self.token(TokenKind::CloseBracket, 1));
+        }
+        self.node(NodeKind::Array, start, children)
+    }
+
+    fn field(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        if self.recognize(parser::include).is_some() {
+            let include = self.include();
+            return self.node(NodeKind::Field, start, vec![include]);
+        }
+
+        let mut children = vec![self.key()];
+        self.trivia(&mut children);
+        if self.at("+=") {
+            children.push(self.token(TokenKind::PlusEquals, 2));
+        } else if self.at(":") {
+            children.push(self.token(TokenKind::Colon, 1));
+        } else if self.at("=") {
+            children.push(self.token(TokenKind::Equals, 1));
+        }
+        self.trivia(&mut children);
+        children.push(self.value());
+        self.node(NodeKind::Field, start, children)
+    }
+
+    fn include(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        let mut children = vec![self.token(TokenKind::Keyword, "include".len())];
+        self.trivia(&mut children);
+        let required = self.at("required(");
+        if required {
+            children.push(self.token(TokenKind::Keyword, "required".len()));
+            children.push(self.token(TokenKind::OpenParen, 1));
+        }
+        self.include_resource(&mut children);
+        if required && self.at(")") {
+            children.push(self.token(TokenKind::CloseParen, 1));
+        }
+        self.node(NodeKind::Include, start, children)
+    }
+
+    /// Adds the resource of an include, either a quoted string or one wrapped in `file(...)` or a similar keyword.
+    fn include_resource(&mut self, children: &mut Vec<SyntaxElement<'a>>) {
+        let explicit = !self.at("\"");
+        if explicit {
+            let keyword = self.rest().find('(').unwrap_or(0);
+            children.push(self.token(TokenKind::Keyword, keyword));
+            children.push(self.token(TokenKind::OpenParen, 1));
+        }
+        if let Some(len) = self.recognize(parser::quoted_string) {
+            children.push(self.token(TokenKind::QuotedString, len));
+        }
+        if explicit && self.at(")") {
+            children.push(self.token(TokenKind::CloseParen, 1));
+        }
+    }
+
+    /// Adds a path expression, keeping the unquoted parts along with the dots separating the keys.
+    fn key(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        let end = start + self.recognize(parser::path_expression).unwrap_or(0);
+        let mut children = vec![];
+        while self.offset < end {
+            if let Some(len) = self.recognize(parser::quoted_string) {
+                children.push(self.token(TokenKind::QuotedString, len));
+            } else if let Some(len) = self.recognize(parser::unquoted_string) {
+                children.push(self.token(TokenKind::UnquotedString, len));
+            } else {
+                let rest = self.rest();
+                let whitespace = rest.len() - rest.trim_start_matches(is_inline_whitespace).len();
+                children.push(self.token(TokenKind::Whitespace, whitespace.clamp(1, end - self.offset)));
+            }
+        }
+        self.node(NodeKind::Key, start, children)
+    }
+
+    /// Adds a value, along with the values concatenated to it on the same line.
+    fn value(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        if self.recognize(parser::value_include).is_some() {
+            let include = self.include();
+            return self.node(NodeKind::Value, start, vec![include]);
+        }
+
+        let mut children = vec![self.concatenation_element()];
+        loop {
+            let rest = self.rest();
+            let whitespace = rest.len() - rest.trim_start_matches(is_inline_whitespace).len();
+            let next = Builder {
+                source: self.source,
+                offset: self.offset + whitespace,
+            };
+            if !next.starts_concatenation_element() {
+                break;
+            }
+            if whitespace > 0 {
+                children.push(self.token(TokenKind::Whitespace, whitespace));
+            }
+            children.push(self.concatenation_element());
+        }
+        self.node(NodeKind::Value, start, children)
+    }
+
+    fn starts_concatenation_element(&self) -> bool {
+        match self.rest().chars().next() {
+            Some('[' | '{' | '"') => true,
+            Some('$') => self.at("${"),
+            Some(_) => self.recognize(parser::number).is_some() || self.recognize(parser::unquoted_string).is_some(),
+            None => false,
+        }
+    }
+
+    fn concatenation_element(&mut self) -> SyntaxElement<'a> {
+        if self.at("[") {
+            return self.array();
+        } else if self.at("{") {
+            return self.object();
+        } else if self.at("${") {
+            return self.substitution();
+        }
+
+        let keywords = [
+            ("null", TokenKind::Null),
+            ("true", TokenKind::True),
+            ("false", TokenKind::False),
+        ];
+        if let Some((keyword, kind)) = keywords.into_iter().find(|(keyword, _)| self.at(keyword)) {
+            return self.token(kind, keyword.len());
+        }
+        if let Some(len) = self.recognize(parser::number) {
+            return self.token(TokenKind::Number, len);
+        }
+        if let Some(len) = self.recognize(parser::unquoted_string) {
+            return self.token(TokenKind::UnquotedString, len);
+        }
+        if let Some(len) = self.recognize(parser::multiline_string) {
+            return self.token(TokenKind::MultilineString, len);
+        }
+        if let Some(len) = self.recognize(parser::quoted_string) {
+            return self.token(TokenKind::QuotedString, len);
+        }
+        let len = self.rest().chars().next().map_or(0, char::len_utf8);
+        self.token(TokenKind::UnquotedString, len)
+    }
+
+    fn substitution(&mut self) -> SyntaxElement<'a> {
+        let start = self.offset;
+        let opening = if self.at("${?") { 3 } else { 2 };
+        let mut children = vec![self.token(TokenKind::SubstitutionStart, opening)];
+        children.push(self.key());
+        if self.at("}") {
+            children.push(self.token(TokenKind::CloseBrace, 1));
+        }
+        self.node(NodeKind::Substitution, start, children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// The documents in the fixture corpus along with the syntax they are written in.
+    fn fixtures() -> Vec<(String, ConfigSyntax)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests");
+        let mut fixtures: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .map(|path| {
+                let syntax = match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("json") => ConfigSyntax::Json,
+                    _ => ConfigSyntax::Conf,
+                };
+                (fs::read_to_string(path).unwrap(), syntax)
+            })
+            .collect();
+        fixtures.sort_by(|(a, _), (b, _)| a.cmp(b));
+        fixtures
+    }
+
+    fn kinds(node: &SyntaxNode<'_>) -> Vec<TokenKind> {
+        node.tokens().into_iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn test_round_trip_fixtures() {
+        for (content, syntax) in fixtures() {
+            let tree = parse_with_syntax(&content, syntax).unwrap();
+            assert_eq!(tree.to_string(), content);
+            assert_eq!(
+                tree.span,
+                Span {
+                    start: 0,
+                    end: content.len()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        let tree = parse("a.\"b\" : [1, ${?c}] // comment\n").unwrap();
+        assert_eq!(
+            kinds(&tree),
+            vec![
+                TokenKind::UnquotedString,
+                TokenKind::QuotedString,
+                TokenKind::Whitespace,
+                TokenKind::Colon,
+                TokenKind::Whitespace,
+                TokenKind::OpenBracket,
+                TokenKind::Number,
+                TokenKind::Comma,
+                TokenKind::Whitespace,
+                TokenKind::SubstitutionStart,
+                TokenKind::UnquotedString,
+                TokenKind::CloseBrace,
+                TokenKind::CloseBracket,
+                TokenKind::Whitespace,
+                TokenKind::Comment,
+                TokenKind::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include_tokens() {
+        let tree = parse("include \"a\"\ninclude required(file(\"b\"))\nc = include \"d\"").unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "include \"a\"\ninclude required(file(\"b\"))\nc = include \"d\""
+        );
+        assert_eq!(
+            kinds(&tree),
+            vec![
+                TokenKind::Keyword,
+                TokenKind::Whitespace,
+                TokenKind::QuotedString,
+                TokenKind::Newline,
+                TokenKind::Keyword,
+                TokenKind::Whitespace,
+                TokenKind::Keyword,
+                TokenKind::OpenParen,
+                TokenKind::Keyword,
+                TokenKind::OpenParen,
+                TokenKind::QuotedString,
+                TokenKind::CloseParen,
+                TokenKind::CloseParen,
+                TokenKind::Newline,
+                TokenKind::UnquotedString,
+                TokenKind::Whitespace,
+                TokenKind::Equals,
+                TokenKind::Whitespace,
+                TokenKind::UnquotedString,
+                TokenKind::Whitespace,
+                TokenKind::QuotedString,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nodes() {
+        let tree = parse("{ a = x y, include file(\"f.conf\") }").unwrap();
+        let [SyntaxElement::Node(object)] = tree.children.as_slice() else {
+            panic!("Expected a single root object, got {tree:?}");
+        };
+        assert_eq!(object.kind, NodeKind::Object);
+        let fields: Vec<_> = object
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].kind, NodeKind::Field);
+        assert_eq!(fields[0].to_string(), "a = x y");
+        assert_eq!(fields[0].span, Span { start: 2, end: 9 });
+        assert_eq!(fields[1].to_string(), "include file(\"f.conf\")");
+        assert!(matches!(&fields[1].children[..], [SyntaxElement::Node(include)] if include.kind == NodeKind::Include));
+    }
+
+    #[test]
+    fn test_invalid_document() {
+        assert!(matches!(parse("a = [1 2"), Err(HoconError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_properties_are_unsupported() {
+        assert!(matches!(
+            parse_with_syntax("! comment\nkey value", ConfigSyntax::Properties),
+            Err(HoconError::UnsupportedSyntax { .. })
+        ));
+    }
+
+    /// Generates the text of a value, formatted in one of the many ways the syntax allows.
+    fn value() -> impl Strategy<Value = String> {
+        let leaf = prop_oneof![
+            Just("null".to_string()),
+            Just("true".to_string()),
+            any::<i64>().prop_map(|value| value.to_string()),
+            any::<f64>().prop_map(|value| format!("{value:?}")),
+            "[a-z][a-z0-9_.-]{0,8}",
+            "[ -~]{0,8}".prop_map(|value| format!("{value:?}")),
+            "[a-z\n \"]{0,8}".prop_map(|value| format!("\"\"\"{value}\"\"\"")),
+            "[a-z]{1,4}(\\.[a-z]{1,4}){0,2}".prop_map(|path| format!("${{{path}}}")),
+            "[a-z]{1,4}".prop_map(|path| format!("${{?{path}}}")),
+        ];
+        leaf.prop_recursive(4, 32, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 1..4).prop_map(|values| values.join(" ")),
+                (prop::collection::vec((trivia(), inner.clone()), 0..4), separator())
+                    .prop_map(|(values, separator)| format!("[{}]", join(values, &separator))),
+                prop::collection::vec((trivia(), field(inner)), 0..4)
+                    .prop_map(|fields| format!("{{{}\n}}", join(fields, "\n"))),
+            ]
+        })
+    }
+
+    fn field(value: impl Strategy<Value = String>) -> impl Strategy<Value = String> {
+        let key = prop_oneof![
+            "[a-z][a-z0-9_-]{0,6}",
+            "[a-z]{1,4}(\\.[a-z]{1,4}){1,2}",
+            "[ -~]{0,6}".prop_map(|key| format!("{key:?}"))
+        ];
+        let assignment = prop_oneof![Just(" = "), Just("="), Just(": "), Just(" : "), Just(" += ")];
+        (key, assignment, value).prop_map(|(key, assignment, value)| format!("{key}{assignment}{value}"))
+    }
+
+    fn separator() -> impl Strategy<Value = String> {
+        prop_oneof![Just(","), Just(", "), Just(" ,\n"), Just("\n"), Just("\r\n")].prop_map(String::from)
+    }
+
+    /// Generates whitespace and comments which may precede a value or field.
+    fn trivia() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop_oneof![Just(" "), Just("\t"), Just("\n"), Just("# comment\n"), Just("//\n")],
+            0..3,
+        )
+        .prop_map(|trivia| trivia.concat())
+    }
+
+    fn join(elements: Vec<(String, String)>, separator: &str) -> String {
+        let elements: Vec<_> = elements
+            .into_iter()
+            .map(|(trivia, element)| trivia + &element)
+            .collect();
+        elements.join(separator)
+    }
+
+    fn document() -> impl Strategy<Value = String> {
+        (
+            prop::collection::vec((trivia(), field(value())), 0..6),
+            separator(),
+            trivia(),
+            any::<bool>(),
+        )
+            .prop_map(|(fields, separator, trailing, braces)| match braces {
+                true => format!("{{{}}}{trailing}", join(fields, &separator)),
+                false => format!("{}{trailing}", join(fields, &separator)),
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip_generated(document in document()) {
+            prop_assume!(crate::parser::parse(&document).is_ok());
+            prop_assert_eq!(parse(&document).unwrap().to_string(), document);
+        }
+
+        #[test]
+        fn test_round_trip_fixtures_with_trivia(
+            fixture in prop::sample::select(fixtures()),
+            trivia in prop::collection::vec(trivia(), 1..20),
+        ) {
+            let (content, syntax) = fixture;
+            // Adds whitespace and comments at the end of the lines, which keeps valid documents valid.
+            let mut lines = content.split('\n').map(String::from).collect::<Vec<_>>();
+            for (i, trivia) in trivia.into_iter().enumerate() {
+                let line = i % lines.len();
+                lines[line].push_str(trivia.trim_end_matches('\n'));
+            }
+            let document = lines.join("\n");
+            prop_assume!(crate::parser::parse_with_syntax(&document, syntax).is_ok());
+            prop_assert_eq!(parse_with_syntax(&document, syntax).unwrap().to_string(), document);
+        }
+    }
+}